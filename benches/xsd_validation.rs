@@ -0,0 +1,70 @@
+//! Compares validating a feed by compiling its XSD on every request, as the import handler
+//! used to, with validating through a context compiled once and reused.
+//!
+//! Run with `cargo bench --bench xsd_validation`
+
+use std::time::{Duration, Instant};
+
+use libxml::{
+    parser::Parser,
+    schemas::{SchemaParserContext, SchemaValidationContext},
+};
+
+const ITERATIONS: u32 = 500;
+
+const FEED: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<source>
+  <job>
+    <businessProcessId>1001</businessProcessId>
+    <jobCode>TID-1001-FR</jobCode>
+    <title>Préposé à l'entretien ménager</title>
+    <company>Hôtel Tidan</company>
+    <ville>Québec</ville>
+    <province>QC</province>
+    <description>&lt;p&gt;Description&lt;/p&gt;</description>
+    <applyUrl>https://example.com/apply/1001</applyUrl>
+    <applicationFormUrl>https://example.com/form/1001</applicationFormUrl>
+    <typedemploi>Temps-plein</typedemploi>
+  </job>
+</source>"#;
+
+fn main() {
+    let schemas = [
+        "xsd-schemas/xml-tidan.xsd",
+        "xsd-schemas/xml-hotelleriejobs.xsd",
+        "xsd-schemas/xml-zohoquintessence.xsd",
+    ];
+    let parser = Parser::default();
+
+    for schema in schemas {
+        let per_request = time(|| {
+            let mut schema_parser = SchemaParserContext::from_file(schema);
+            let mut validation = SchemaValidationContext::from_parser(&mut schema_parser).unwrap();
+            let document = parser.parse_string(FEED).unwrap();
+            let _ = validation.validate_document(&document);
+        });
+
+        let mut schema_parser = SchemaParserContext::from_file(schema);
+        let mut cached = SchemaValidationContext::from_parser(&mut schema_parser).unwrap();
+        let reused = time(|| {
+            let document = parser.parse_string(FEED).unwrap();
+            let _ = cached.validate_document(&document);
+        });
+
+        println!(
+            "{:<40} compiled per request: {:>9.1?}/req   cached: {:>9.1?}/req   saving: {:>9.1?}/req",
+            schema,
+            per_request,
+            reused,
+            per_request.saturating_sub(reused)
+        );
+    }
+}
+
+fn time(mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
@@ -0,0 +1,38 @@
+use std::sync::Arc;
+
+use axum::{Json, extract::State};
+use serde::Serialize;
+
+use crate::api::AppState;
+
+#[derive(Serialize)]
+pub struct FormatInfo {
+    key: String,
+    schema: String,
+    implemented: bool,
+    profiles: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct FormatsResponse {
+    formats: Vec<FormatInfo>,
+}
+
+pub async fn handler(State(state): State<Arc<AppState>>) -> Json<FormatsResponse> {
+    let formats = state
+        .registry
+        .parsers()
+        .map(|parser| FormatInfo {
+            key: parser.name().to_string(),
+            schema: parser.xsd_file().to_string(),
+            implemented: parser.is_implemented(),
+            profiles: parser
+                .profiles()
+                .into_iter()
+                .map(|profile| profile.to_string())
+                .collect(),
+        })
+        .collect();
+
+    Json(FormatsResponse { formats })
+}
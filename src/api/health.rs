@@ -0,0 +1,113 @@
+use std::sync::Arc;
+
+use axum::{Json, body::Bytes, extract::State, http::StatusCode};
+use serde::Serialize;
+use tracing::warn;
+
+use crate::{
+    api::AppState,
+    utils::{common, registry::Registry},
+};
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Serialize)]
+pub struct HealthResponse {
+    status: &'static str,
+    version: &'static str,
+}
+
+/// Liveness, the process is up and serving requests
+pub async fn healthz() -> Json<HealthResponse> {
+    Json(HealthResponse {
+        status: "ok",
+        version: VERSION,
+    })
+}
+
+#[derive(Serialize)]
+pub struct CheckStatus {
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl From<Result<(), String>> for CheckStatus {
+    fn from(result: Result<(), String>) -> Self {
+        Self {
+            ok: result.is_ok(),
+            error: result.err(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct FormatStatus {
+    key: String,
+    schema: String,
+    #[serde(flatten)]
+    status: CheckStatus,
+}
+
+#[derive(Serialize)]
+pub struct ReadyResponse {
+    ready: bool,
+    version: &'static str,
+    libxml: CheckStatus,
+    formats: Vec<FormatStatus>,
+}
+
+/// Readiness, every enabled format has a usable compiled schema and libxml can parse
+pub async fn readyz(State(state): State<Arc<AppState>>) -> (StatusCode, Json<ReadyResponse>) {
+    // Checking a schema may compile a new context when all cached ones are busy
+    let registry = state.registry.clone();
+    let response = match tokio::task::spawn_blocking(move || check(&registry)).await {
+        Ok(response) => response,
+        Err(e) => {
+            warn!("Readiness check failed: {}", e);
+            ReadyResponse {
+                ready: false,
+                version: VERSION,
+                libxml: CheckStatus::from(Err(e.to_string())),
+                formats: vec![],
+            }
+        }
+    };
+
+    let status = if response.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (status, Json(response))
+}
+
+fn check(registry: &Registry) -> ReadyResponse {
+    let libxml = CheckStatus::from(
+        common::parse_document(&Bytes::from_static(b"<ready/>"))
+            .map(|_| ())
+            .map_err(|e| e.message),
+    );
+
+    let formats: Vec<FormatStatus> = registry
+        .parsers()
+        .map(|parser| FormatStatus {
+            key: parser.name().to_string(),
+            schema: parser.xsd_file().to_string(),
+            status: CheckStatus::from(registry.schemas().check(parser.name())),
+        })
+        .collect();
+
+    let ready = libxml.ok && formats.iter().all(|format| format.status.ok);
+    if !ready {
+        warn!("Service is not ready");
+    }
+
+    ReadyResponse {
+        ready,
+        version: VERSION,
+        libxml,
+        formats,
+    }
+}
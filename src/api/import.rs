@@ -1,237 +1,545 @@
-use axum::{Json, body::Bytes, extract::Multipart, http::StatusCode};
-use log::{info, warn};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    Json,
+    body::Bytes,
+    extract::{Multipart, State, multipart::MultipartError},
+    http::StatusCode,
+};
 use serde::Serialize;
+use tracing::{debug, info, warn};
 
-use crate::utils::{
-    common::{Job, XMLError},
-    parse_xmlgrandio, parse_xmlhotelleriejobs, parse_xmlpscout, parse_xmltidan,
-    parse_xmlzohoquintessence, parse_xmlzohorecruit,
+use crate::{
+    api::AppState,
+    utils::{
+        common::{
+            Duplicate, FieldReport, Job, LabelMatch, MergeConflict, ParseError, ParseErrorKind,
+            ParsedFeed, RejectedJob, SkippedJob, UnmappedLabel, ValidationIssue, Warning, XMLError,
+        },
+        detect::{self, Detection},
+        duplicates::DuplicatePolicy,
+        metrics::UNKNOWN_FORMAT,
+        pipeline::{ImportOptions, Pipeline},
+        province::ProvinceFilter,
+        registry::Registry,
+        worker_pool::PoolError,
+    },
 };
 
 #[derive(Serialize)]
 pub struct ImportResponse {
     success: bool,
     errors: String,
+    error_kind: Option<ParseErrorKind>,
     xml_errors: Vec<XMLError>,
+    detection: Option<Detection>,
+    warnings: Vec<Warning>,
+    skipped: Vec<SkippedJob>,
+    unmapped_categories: Vec<UnmappedLabel>,
+    merge_conflicts: Vec<MergeConflict>,
+    duplicates: Vec<Duplicate>,
+    validation_issues: Vec<ValidationIssue>,
+    rejected_jobs: Vec<RejectedJob>,
+    matched_labels: Vec<LabelMatch>,
+    unused_labels: Vec<UnmappedLabel>,
+    source_fields: Option<FieldReport>,
     jobs: Vec<Job>,
 }
 
-pub async fn handler(multipart: Multipart) -> (StatusCode, Json<ImportResponse>) {
-    let (format, file) = read_multipart(multipart).await;
-
-    if format.is_none() || file.is_none() {
-        warn!(target: "import", "Request to import, format or file is missing");
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(ImportResponse {
-                success: false,
-                errors: "Format or file is missing".to_string(),
-                xml_errors: vec![],
-                jobs: vec![],
-            }),
-        );
+impl ImportResponse {
+    fn failure(error: ParseError) -> Self {
+        Self {
+            success: false,
+            errors: error.message,
+            error_kind: Some(error.kind),
+            xml_errors: error.xml_errors,
+            detection: None,
+            warnings: vec![],
+            skipped: vec![],
+            unmapped_categories: vec![],
+            merge_conflicts: vec![],
+            duplicates: vec![],
+            validation_issues: vec![],
+            rejected_jobs: vec![],
+            matched_labels: vec![],
+            unused_labels: vec![],
+            source_fields: None,
+            jobs: vec![],
+        }
     }
 
-    info!(target: "import", "Request to parse an {:?} file", format.clone().unwrap());
-
-    match format.unwrap().as_str() {
-        "xml-hotelleriejobs" => {
-            let jobs = parse_xmlhotelleriejobs::parse(file.as_ref().unwrap());
-            if let Err(errors) = jobs {
-                warn!(target: "import", "Error parsing file: {:?} (xml-hotelleriejobs)", errors.message);
-                return (
-                    StatusCode::BAD_REQUEST,
-                    Json(ImportResponse {
-                        success: false,
-                        errors: errors.message,
-                        xml_errors: errors.xml_errors,
-                        jobs: vec![],
-                    }),
-                );
-            }
+    fn success(feed: ParsedFeed) -> Self {
+        Self {
+            success: true,
+            errors: "".to_string(),
+            error_kind: None,
+            xml_errors: vec![],
+            detection: None,
+            warnings: feed.warnings,
+            skipped: feed.skipped,
+            unmapped_categories: feed.unmapped_categories,
+            merge_conflicts: feed.merge_conflicts,
+            duplicates: feed.duplicates,
+            validation_issues: feed.validation_issues,
+            rejected_jobs: feed.rejected_jobs,
+            matched_labels: feed.labels.matched,
+            unused_labels: feed.labels.unused,
+            source_fields: feed.source_fields,
+            jobs: feed.jobs,
+        }
+    }
+}
+
+fn status_for(kind: ParseErrorKind) -> StatusCode {
+    match kind {
+        ParseErrorKind::NotImplemented => StatusCode::NOT_IMPLEMENTED,
+        ParseErrorKind::Overloaded => StatusCode::SERVICE_UNAVAILABLE,
+        ParseErrorKind::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        ParseErrorKind::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+        _ => StatusCode::BAD_REQUEST,
+    }
+}
 
-            info!(target: "import", "File parsed successfully");
+fn failure(error: ParseError) -> (StatusCode, ImportResponse) {
+    (status_for(error.kind), ImportResponse::failure(error))
+}
 
-            return (
-                StatusCode::OK,
-                Json(ImportResponse {
-                    success: true,
-                    errors: "".to_string(),
-                    xml_errors: vec![],
-                    jobs: jobs.unwrap(),
-                }),
-            );
-        }
-        "xml-icims" => {
-            warn!(target: "import", "icims format is not implemented yet");
-            return (
-                StatusCode::NOT_IMPLEMENTED,
-                Json(ImportResponse {
-                    success: false,
-                    errors: "icims format is no longer supported".to_string(),
-                    xml_errors: vec![],
-                    jobs: vec![],
-                }),
-            );
+pub async fn handler(
+    State(state): State<Arc<AppState>>,
+    multipart: Multipart,
+) -> (StatusCode, Json<ImportResponse>) {
+    let Upload {
+        format,
+        profile,
+        file,
+        options,
+    } = match read_multipart(multipart, state.max_upload_bytes).await {
+        Ok(upload) => upload,
+        Err(e) => {
+            warn!("Request to import, invalid multipart body: {}", e.message);
+            let (status, response) = failure(e);
+            return finish(&state, None, None, None, status, response);
         }
-        "xml-grandio" => {
-            let jobs = parse_xmlgrandio::parse(file.as_ref().unwrap());
-            if let Err(errors) = jobs {
-                warn!(target: "import", "Error parsing fril: {:?} (xml-grandio)", errors.message);
-                return (
-                    StatusCode::BAD_REQUEST,
-                    Json(ImportResponse {
-                        success: false,
-                        errors: errors.message,
-                        xml_errors: errors.xml_errors,
-                        jobs: vec![],
-                    }),
-                );
-            }
+    };
 
-            info!(target: "import", "File parsed successfully (xml-grandio)");
-            return (
-                StatusCode::OK,
-                Json(ImportResponse {
-                    success: true,
-                    errors: "".to_string(),
-                    xml_errors: vec![],
-                    jobs: jobs.unwrap(),
-                }),
-            );
+    let Some(file) = file else {
+        warn!("Request to import, file is missing");
+        let (status, response) = failure(ParseError::new(
+            ParseErrorKind::MultipartInvalid,
+            "File is missing",
+        ));
+        return finish(&state, format.as_deref(), None, None, status, response);
+    };
+    let bytes = file.len();
+
+    // Detection, validation and mapping are CPU-bound, keep them off the async runtime
+    // Only they are timed, not the upload nor the wait in the queue
+    let registry = state.registry.clone();
+    let pipeline = state.pipeline.clone();
+    let requested = format.clone();
+    let ((status, response), elapsed) = match state
+        .workers
+        .run(move || {
+            let started = Instant::now();
+            let result = process(&registry, &pipeline, requested, profile, file, &options);
+            (result, started.elapsed())
+        })
+        .await
+    {
+        Ok((result, elapsed)) => (result, Some(elapsed)),
+        Err(PoolError::Saturated) => {
+            warn!("Parsing queue is full, rejecting import");
+            let result = failure(ParseError::new(
+                ParseErrorKind::Overloaded,
+                "Too many imports in progress, retry later",
+            ));
+            (result, None)
+        }
+        Err(PoolError::Failed(e)) => {
+            warn!("Parsing task failed: {}", e);
+            let result = failure(ParseError::new(
+                ParseErrorKind::Internal,
+                "Parsing the file failed unexpectedly",
+            ));
+            (result, None)
         }
-        "xml-tidan" => {
-            let jobs = parse_xmltidan::parse(file.as_ref().unwrap());
-            if let Err(errors) = jobs {
-                warn!(target: "import", "Error parsing file: {:?} (xml-tidan)", errors.message);
-                return (
-                    StatusCode::BAD_REQUEST,
-                    Json(ImportResponse {
-                        success: false,
-                        errors: errors.message,
-                        xml_errors: errors.xml_errors,
-                        jobs: vec![],
-                    }),
+    };
+
+    let format = format.or_else(|| {
+        response
+            .detection
+            .as_ref()
+            .map(|detection| detection.format.clone())
+    });
+    finish(
+        &state,
+        format.as_deref(),
+        elapsed,
+        Some(bytes),
+        status,
+        response,
+    )
+}
+
+/// Log and record metrics for the outcome of an import, once per request whatever the result
+/// `elapsed` is the processing time, `None` when the upload never reached a worker, and
+/// `bytes` the size of the file, `None` when none was read
+fn finish(
+    state: &AppState,
+    format: Option<&str>,
+    elapsed: Option<Duration>,
+    bytes: Option<usize>,
+    status: StatusCode,
+    response: ImportResponse,
+) -> (StatusCode, Json<ImportResponse>) {
+    let outcome = response.error_kind.map_or("ok", |kind| kind.as_str());
+
+    info!(
+        format = format.unwrap_or(UNKNOWN_FORMAT),
+        bytes,
+        jobs = response.jobs.len(),
+        warnings = response.warnings.len(),
+        skipped = response.skipped.len(),
+        status = status.as_u16(),
+        outcome,
+        duration_ms = elapsed.map(|elapsed| elapsed.as_millis() as u64),
+        "Import finished"
+    );
+
+    // Only registered format keys become label values
+    let label = format
+        .and_then(|format| state.registry.get(format))
+        .map_or(UNKNOWN_FORMAT, |parser| parser.name());
+    state.metrics.observe_import(
+        label,
+        outcome,
+        elapsed.map(|elapsed| elapsed.as_secs_f64()),
+        bytes,
+        response.jobs.len(),
+    );
+
+    (status, Json(response))
+}
+
+fn process(
+    registry: &Registry,
+    pipeline: &Pipeline,
+    format: Option<String>,
+    profile: Option<String>,
+    file: Bytes,
+    options: &ImportOptions,
+) -> (StatusCode, ImportResponse) {
+    // Without an explicit format, sniff the document to find which one it is
+    let mut detection: Option<Detection> = None;
+    let format = match format {
+        Some(format) => format,
+        None => match detect::detect(registry, &file) {
+            Ok(detected) => {
+                info!(
+                    "Detected format {} (confidence {:.2}, ambiguous: {})",
+                    detected.format, detected.confidence, detected.ambiguous
                 );
+                let format = detected.format.clone();
+                detection = Some(detected);
+                format
+            }
+            Err(e) => {
+                warn!("Format detection failed: {}", e.message);
+                return failure(e);
             }
+        },
+    };
 
-            info!(target: "import", "File parsed successfully (xml-tidan)");
-            return (
-                StatusCode::OK,
-                Json(ImportResponse {
-                    success: true,
-                    errors: "".to_string(),
-                    xml_errors: vec![],
-                    jobs: jobs.unwrap(),
-                }),
-            );
-        }
-        "xml-pscout" => {
-            let jobs = parse_xmlpscout::parse(file.as_ref().unwrap());
-            if let Err(errors) = jobs {
-                warn!(target: "import", "Error parsing file: {:?} (xml-pscout)", errors.message);
-                return (
-                    StatusCode::BAD_REQUEST,
-                    Json(ImportResponse {
-                        success: false,
-                        errors: errors.message,
-                        xml_errors: errors.xml_errors,
-                        jobs: vec![],
-                    }),
-                );
+    debug!("Request to parse an {:?} file", format);
+
+    let Some(parser) = registry.get(&format) else {
+        warn!("Format is not supported");
+        return failure(ParseError::new(
+            ParseErrorKind::UnsupportedFormat,
+            "Format is not supported",
+        ));
+    };
+
+    if !parser.is_implemented() {
+        warn!("{} format is not implemented yet", format);
+        return failure(ParseError::new(
+            ParseErrorKind::NotImplemented,
+            format!("{} format is not implemented yet", format),
+        ));
+    }
+
+    let parser = match profile.as_deref() {
+        Some(profile) => match parser.profile(profile) {
+            Some(parser) => parser,
+            None => {
+                let message = if parser.profiles().is_empty() {
+                    format!(
+                        "{} format has no profiles, they only apply to formats described by a mapping file",
+                        format
+                    )
+                } else {
+                    format!("{} format has no profile {}", format, profile)
+                };
+                warn!("{}", message);
+                return failure(ParseError::new(ParseErrorKind::UnsupportedFormat, message));
             }
+        },
+        None => parser,
+    };
 
-            info!(target: "import", "File parsed successfully (xml-pscout)");
-            return (
-                StatusCode::OK,
-                Json(ImportResponse {
-                    success: true,
-                    errors: "".to_string(),
-                    xml_errors: vec![],
-                    jobs: jobs.unwrap(),
-                }),
-            );
-        }
-        "xml-zohoquintessence" => {
-            let jobs = parse_xmlzohoquintessence::parse(file.as_ref().unwrap());
-            if let Err(errors) = jobs {
-                warn!(target: "import", "Error parsing file: {:?} (xml-zohoquintessence)", errors.message);
-                return (
-                    StatusCode::BAD_REQUEST,
-                    Json(ImportResponse {
-                        success: false,
-                        errors: errors.message,
-                        xml_errors: errors.xml_errors,
-                        jobs: vec![],
-                    }),
+    let (status, mut response) = match parser.parse(&file, registry.schemas(), options.lenient) {
+        Ok(parsed) => {
+            if !parsed.rejected.is_empty() {
+                warn!(
+                    "Lenient import left out {} invalid jobs",
+                    parsed.rejected.len()
                 );
             }
-
-            info!(target: "import", "File parsed successfully (xml-zohoquintessence)");
-            return (
-                StatusCode::OK,
-                Json(ImportResponse {
-                    success: true,
-                    errors: "".to_string(),
-                    xml_errors: vec![],
-                    jobs: jobs.unwrap(),
-                }),
-            );
-        }
-        "xml-zohorecruit" => {
-            let jobs = parse_xmlzohorecruit::parse(file.as_ref().unwrap());
-            if let Err(errors) = jobs {
-                warn!(target: "import", "Error parsing file: {:?} (xml-zohorecruit)", errors.message);
-                return (
-                    StatusCode::BAD_REQUEST,
-                    Json(ImportResponse {
-                        success: false,
-                        errors: errors.message,
-                        xml_errors: errors.xml_errors,
-                        jobs: vec![],
-                    }),
+            let mut feed = pipeline.run(&format, parsed.jobs, options);
+            feed.rejected_jobs = parsed.rejected;
+            feed.labels = parsed.labels;
+            feed.source_fields = Some(parsed.fields);
+            if options.duplicates == DuplicatePolicy::Reject && !feed.duplicates.is_empty() {
+                warn!(
+                    "Rejecting feed with {} duplicate jobs",
+                    feed.duplicates.len()
                 );
+                let (status, mut response) = failure(ParseError::new(
+                    ParseErrorKind::DuplicateJobs,
+                    format!("Feed has {} duplicate jobs", feed.duplicates.len()),
+                ));
+                response.duplicates = feed.duplicates;
+                (status, response)
+            } else {
+                (StatusCode::OK, ImportResponse::success(feed))
             }
-
-            info!(target: "import", "File parsed successfully (xml-zohorecruit)");
-            return (
-                StatusCode::OK,
-                Json(ImportResponse {
-                    success: true,
-                    errors: "".to_string(),
-                    xml_errors: vec![],
-                    jobs: jobs.unwrap(),
-                }),
-            );
         }
-        _ => {
-            warn!(target: "import", "Format is not supported");
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(ImportResponse {
-                    success: false,
-                    errors: "Format is not supported".to_string(),
-                    xml_errors: vec![],
-                    jobs: vec![],
-                }),
+        Err(errors) => {
+            warn!(
+                "Error parsing file: {:?} ({:?}, {})",
+                errors.message, errors.kind, format
             );
+            (status_for(errors.kind), ImportResponse::failure(errors))
         }
-    }
+    };
+    response.detection = detection;
+
+    (status, response)
 }
 
-async fn read_multipart(mut multipart: Multipart) -> (Option<String>, Option<Bytes>) {
+/// Fields of the `/import` multipart body
+struct Upload {
+    format: Option<String>,
+    /// Client profile of the format, sent on its own or as `format:profile`
+    profile: Option<String>,
+    file: Option<Bytes>,
+    options: ImportOptions,
+}
+
+async fn read_multipart(
+    mut multipart: Multipart,
+    max_upload_bytes: usize,
+) -> Result<Upload, ParseError> {
     let mut format: Option<String> = None;
+    let mut profile: Option<String> = None;
     let mut file: Option<Bytes> = None;
+    let mut options = ImportOptions::default();
 
-    while let Some(field) = multipart.next_field().await.unwrap() {
-        let name = field.name().unwrap().to_string();
+    let invalid = |e: MultipartError| {
+        if e.status() == StatusCode::PAYLOAD_TOO_LARGE {
+            ParseError::new(
+                ParseErrorKind::PayloadTooLarge,
+                format!("Upload is larger than the {} bytes limit", max_upload_bytes),
+            )
+        } else {
+            ParseError::new(ParseErrorKind::MultipartInvalid, e.body_text())
+        }
+    };
+
+    while let Some(field) = multipart.next_field().await.map_err(invalid)? {
+        let Some(name) = field.name().map(|name| name.to_string()) else {
+            continue;
+        };
 
         if name == "format" {
-            format = Some(field.text().await.unwrap());
+            let value = field.text().await.map_err(invalid)?;
+            // An empty format field is treated as omitted so the format gets detected
+            if !value.trim().is_empty() {
+                format = Some(value.trim().to_string());
+            }
+        } else if name == "profile" {
+            let value = field.text().await.map_err(invalid)?;
+            if !value.trim().is_empty() {
+                profile = Some(value.trim().to_string());
+            }
         } else if name == "file" {
-            file = Some(field.bytes().await.unwrap());
+            file = Some(field.bytes().await.map_err(invalid)?);
+        } else if name == "provinces" {
+            let value = field.text().await.map_err(invalid)?;
+            if !value.trim().is_empty() {
+                options.provinces = Some(ProvinceFilter::parse(&value).map_err(|e| {
+                    ParseError::new(
+                        ParseErrorKind::MultipartInvalid,
+                        format!("Invalid provinces field: {}", e),
+                    )
+                })?);
+            }
+        } else if name == "duplicates" {
+            let value = field.text().await.map_err(invalid)?;
+            if !value.trim().is_empty() {
+                options.duplicates = value.parse().map_err(|e| {
+                    ParseError::new(
+                        ParseErrorKind::MultipartInvalid,
+                        format!("Invalid duplicates field: {}", e),
+                    )
+                })?;
+            }
+        } else if name == "near_duplicates" {
+            let value = field.text().await.map_err(invalid)?;
+            options.near_duplicates = flag(&name, &value)?;
+        } else if name == "lenient" {
+            let value = field.text().await.map_err(invalid)?;
+            options.lenient = flag(&name, &value)?;
         }
     }
 
-    (format, file)
+    let (format, profile) = split_profile(format, profile)?;
+
+    Ok(Upload {
+        format,
+        profile,
+        file,
+        options,
+    })
+}
+
+/// Take the profile out of `xml-zohorecruit:residences-xyz` keys, in the format or the profile
+/// field, checking both fields agree
+fn split_profile(
+    format: Option<String>,
+    profile: Option<String>,
+) -> Result<(Option<String>, Option<String>), ParseError> {
+    let conflict = |message: String| ParseError::new(ParseErrorKind::MultipartInvalid, message);
+
+    let (format, format_profile) = match format {
+        Some(format) => match format.split_once(':') {
+            Some((format, profile)) => (Some(format.to_string()), Some(profile.to_string())),
+            None => (Some(format), None),
+        },
+        None => (None, None),
+    };
+    let (profile_format, profile) = match profile {
+        Some(profile) => match profile.split_once(':') {
+            Some((format, profile)) => (Some(format.to_string()), Some(profile.to_string())),
+            None => (None, Some(profile)),
+        },
+        None => (None, None),
+    };
+
+    let format = match (format, profile_format) {
+        (Some(format), Some(other)) if format != other => {
+            return Err(conflict(format!(
+                "Profile of format {} sent with format {}",
+                other, format
+            )));
+        }
+        (format, other) => format.or(other),
+    };
+    let profile = match (format_profile, profile) {
+        (Some(profile), Some(other)) if profile != other => {
+            return Err(conflict(format!(
+                "Format names profile {} but the profile field is {}",
+                profile, other
+            )));
+        }
+        (profile, other) => profile.or(other),
+    };
+
+    Ok((format, profile))
+}
+
+/// Boolean multipart field, empty counts as false
+fn flag(name: &str, value: &str) -> Result<bool, ParseError> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "" | "false" | "0" => Ok(false),
+        "true" | "1" => Ok(true),
+        _ => Err(ParseError::new(
+            ParseErrorKind::MultipartInvalid,
+            format!("Invalid {} field: expected true or false", name),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(format: Option<&str>, profile: Option<&str>) -> (Option<String>, Option<String>) {
+        split_profile(format.map(String::from), profile.map(String::from)).unwrap()
+    }
+
+    fn owned(format: Option<&str>, profile: Option<&str>) -> (Option<String>, Option<String>) {
+        (format.map(String::from), profile.map(String::from))
+    }
+
+    #[test]
+    fn format_without_profile_is_kept() {
+        assert_eq!(
+            split(Some("xml-grandio"), None),
+            owned(Some("xml-grandio"), None)
+        );
+        assert_eq!(split(None, None), owned(None, None));
+    }
+
+    #[test]
+    fn profile_is_taken_from_either_field() {
+        let expected = owned(Some("xml-zohorecruit"), Some("residences-xyz"));
+        assert_eq!(
+            split(Some("xml-zohorecruit:residences-xyz"), None),
+            expected
+        );
+        assert_eq!(
+            split(None, Some("xml-zohorecruit:residences-xyz")),
+            expected
+        );
+        assert_eq!(
+            split(Some("xml-zohorecruit"), Some("residences-xyz")),
+            expected
+        );
+        assert_eq!(
+            split(
+                Some("xml-zohorecruit:residences-xyz"),
+                Some("residences-xyz")
+            ),
+            expected
+        );
+    }
+
+    #[test]
+    fn profile_alone_leaves_the_format_to_detection() {
+        assert_eq!(
+            split(None, Some("residences-xyz")),
+            owned(None, Some("residences-xyz"))
+        );
+    }
+
+    #[test]
+    fn conflicting_fields_are_rejected() {
+        let formats = split_profile(
+            Some("xml-grandio".to_string()),
+            Some("xml-zohorecruit:residences-xyz".to_string()),
+        )
+        .unwrap_err();
+        assert_eq!(formats.kind, ParseErrorKind::MultipartInvalid);
+
+        let profiles = split_profile(
+            Some("xml-zohorecruit:residences-xyz".to_string()),
+            Some("residences-abc".to_string()),
+        )
+        .unwrap_err();
+        assert_eq!(profiles.kind, ParseErrorKind::MultipartInvalid);
+    }
 }
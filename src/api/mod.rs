@@ -1,2 +1,21 @@
+use std::sync::Arc;
+
+use crate::utils::{
+    metrics::Metrics, pipeline::Pipeline, registry::Registry, worker_pool::WorkerPool,
+};
+
+pub mod formats;
+pub mod health;
 pub mod index;
-pub mod import;
\ No newline at end of file
+pub mod import;
+pub mod metrics;
+
+/// Shared state handed to every handler
+pub struct AppState {
+    pub registry: Arc<Registry>,
+    pub pipeline: Arc<Pipeline>,
+    pub workers: WorkerPool,
+    pub metrics: Metrics,
+    /// Body size limit, named when an upload goes over it
+    pub max_upload_bytes: usize,
+}
@@ -0,0 +1,358 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::Deserialize;
+use tracing_subscriber::EnvFilter;
+
+use crate::utils::merge::MergeKey;
+
+/// Server configuration
+///
+/// Values come from an optional TOML file (path in `HJ2_CONFIG`), then environment variables
+/// override the file. Every key has a default so the server starts without any configuration.
+///
+/// | Key                  | Environment variable        | Default               |
+/// |----------------------|-----------------------------|-----------------------|
+/// | `listen_addr`        | `HJ2_LISTEN_ADDR`           | `0.0.0.0:80`          |
+/// | `schema_dir`         | `HJ2_SCHEMA_DIR`            | `xsd-schemas`         |
+/// | `max_upload_bytes`   | `HJ2_MAX_UPLOAD_BYTES`      | 10 MiB                |
+/// | `log_level`          | `HJ2_LOG_LEVEL`, `RUST_LOG` | `info`                |
+/// | `log_format`         | `HJ2_LOG_FORMAT`            | `json`                |
+/// | `log_dir`            | `HJ2_LOG_DIR`               | none                  |
+/// | `enabled_formats`    | `HJ2_ENABLED_FORMATS`       | every format          |
+/// | `parse_workers`      | `HJ2_PARSE_WORKERS`         | number of CPUs        |
+/// | `parse_queue_limit`  | `HJ2_PARSE_QUEUE_LIMIT`     | `64`                  |
+/// | `mapping_dir`        | `HJ2_MAPPING_DIR`           | `mappings/formats`    |
+/// | `category_map`       | `HJ2_CATEGORY_MAP`          | built-in table        |
+/// | `allowed_provinces`  | `HJ2_ALLOWED_PROVINCES`     | iCIMS: QC only        |
+/// | `merge_translations` | `HJ2_MERGE_TRANSLATIONS`    | Grandio, Tidan, iCIMS |
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub listen_addr: SocketAddr,
+    /// Directory holding the `xml-*.xsd` files, relative paths in the config file are
+    /// resolved from the directory of that file
+    pub schema_dir: PathBuf,
+    pub max_upload_bytes: usize,
+    /// Level or `RUST_LOG`-style filter directives (e.g. `info,hj2_api_xml_rust=debug`)
+    pub log_level: String,
+    pub log_format: LogFormat,
+    /// When set, logs are also written to a daily rotated file in this directory
+    pub log_dir: Option<PathBuf>,
+    /// Format keys accepted by `/import`, `None` enables every registered format
+    pub enabled_formats: Option<Vec<String>>,
+    pub parse_workers: usize,
+    pub parse_queue_limit: usize,
+    /// Directory of the `*.toml` format mappings, formats described without Rust code
+    pub mapping_dir: PathBuf,
+    /// TOML table mapping source category labels to canonical categories, see
+    /// `mappings/categories.toml` for the format
+    pub category_map: Option<PathBuf>,
+    /// Provinces kept per format key, other jobs are reported as skipped
+    /// Configured formats replace their default only, `["*"]` keeps every province
+    /// A request can replace the list with its `provinces` field
+    pub allowed_provinces: HashMap<String, Vec<String>>,
+    /// Formats sending one record per language, and how the records of a job are matched
+    pub merge_translations: HashMap<String, MergeKey>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            listen_addr: SocketAddr::from(([0, 0, 0, 0], 80)),
+            schema_dir: PathBuf::from("xsd-schemas"),
+            max_upload_bytes: 10 * 1024 * 1024,
+            log_level: "info".to_string(),
+            log_format: LogFormat::Json,
+            log_dir: None,
+            enabled_formats: None,
+            parse_workers: std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(4),
+            parse_queue_limit: 64,
+            mapping_dir: PathBuf::from("mappings/formats"),
+            category_map: None,
+            allowed_provinces: default_allowed_provinces(),
+            merge_translations: HashMap::from([
+                ("xml-grandio".to_string(), MergeKey::LanguageSuffix),
+                ("xml-tidan".to_string(), MergeKey::LanguageSuffix),
+                ("xml-icims".to_string(), MergeKey::LanguageSuffix),
+            ]),
+        }
+    }
+}
+
+impl Config {
+    /// Load the configuration file and environment, then validate the result
+    pub fn load() -> Result<Self, String> {
+        let mut config = match std::env::var("HJ2_CONFIG") {
+            Ok(path) => Self::from_file(Path::new(&path))?,
+            Err(_) => Self::default(),
+        };
+
+        config.apply_env()?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    fn from_file(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Could not read config file {}: {}", path.display(), e))?;
+        let mut config: Config = toml::from_str(&content)
+            .map_err(|e| format!("Invalid config file {}: {}", path.display(), e))?;
+        // A table in the file replaces the whole default map, put back the formats it omits
+        let configured = std::mem::take(&mut config.allowed_provinces);
+        config.allowed_provinces = default_allowed_provinces();
+        config.allowed_provinces.extend(configured);
+
+        if let Some(parent) = path.parent() {
+            for dir in [&mut config.schema_dir, &mut config.mapping_dir] {
+                if dir.is_relative() {
+                    *dir = parent.join(&*dir);
+                }
+            }
+            for path in [config.log_dir.as_mut(), config.category_map.as_mut()]
+                .into_iter()
+                .flatten()
+            {
+                if path.is_relative() {
+                    *path = parent.join(&*path);
+                }
+            }
+        }
+
+        Ok(config)
+    }
+
+    fn apply_env(&mut self) -> Result<(), String> {
+        if let Some(value) = env_parse("HJ2_LISTEN_ADDR")? {
+            self.listen_addr = value;
+        }
+        if let Some(value) = env_parse("HJ2_SCHEMA_DIR")? {
+            self.schema_dir = value;
+        }
+        if let Some(value) = env_parse("HJ2_MAX_UPLOAD_BYTES")? {
+            self.max_upload_bytes = value;
+        }
+        if let Some(value) = env_parse("HJ2_LOG_LEVEL")?.or(env_parse("RUST_LOG")?) {
+            self.log_level = value;
+        }
+        if let Some(value) = env_parse("HJ2_LOG_FORMAT")? {
+            self.log_format = value;
+        }
+        if let Some(value) = env_parse("HJ2_LOG_DIR")? {
+            self.log_dir = Some(value);
+        }
+        if let Some(value) = env_parse::<String>("HJ2_ENABLED_FORMATS")? {
+            self.enabled_formats = Some(
+                value
+                    .split(',')
+                    .map(|format| format.trim().to_string())
+                    .filter(|format| !format.is_empty())
+                    .collect(),
+            );
+        }
+        if let Some(value) = env_parse("HJ2_PARSE_WORKERS")? {
+            self.parse_workers = value;
+        }
+        if let Some(value) = env_parse("HJ2_PARSE_QUEUE_LIMIT")? {
+            self.parse_queue_limit = value;
+        }
+        if let Some(value) = env_parse("HJ2_MAPPING_DIR")? {
+            self.mapping_dir = value;
+        }
+        if let Some(value) = env_parse("HJ2_CATEGORY_MAP")? {
+            self.category_map = Some(value);
+        }
+        // Only the formats named replace their default
+        if let Some(value) = env_parse::<String>("HJ2_ALLOWED_PROVINCES")? {
+            self.allowed_provinces
+                .extend(parse_allowed_provinces(&value)?);
+        }
+        // xml-grandio=language-suffix;xml-tidan=language-suffix
+        if let Some(value) = env_parse::<String>("HJ2_MERGE_TRANSLATIONS")? {
+            self.merge_translations = value
+                .split(';')
+                .filter(|entry| !entry.trim().is_empty())
+                .map(|entry| {
+                    let invalid = |reason: &str| {
+                        format!(
+                            "Invalid value {:?} for HJ2_MERGE_TRANSLATIONS: {}",
+                            entry, reason
+                        )
+                    };
+                    let (format, key) = entry
+                        .split_once('=')
+                        .ok_or_else(|| invalid("expected format=KEY"))?;
+                    Ok((
+                        format.trim().to_string(),
+                        key.parse().map_err(|e: String| invalid(&e))?,
+                    ))
+                })
+                .collect::<Result<_, String>>()?;
+        }
+
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if !self.schema_dir.is_dir() {
+            return Err(format!(
+                "schema_dir {} is not a directory",
+                self.schema_dir.display()
+            ));
+        }
+        if !self.mapping_dir.is_dir() {
+            return Err(format!(
+                "mapping_dir {} is not a directory",
+                self.mapping_dir.display()
+            ));
+        }
+        if self.max_upload_bytes == 0 {
+            return Err("max_upload_bytes must be greater than 0".to_string());
+        }
+        if self.parse_workers == 0 {
+            return Err("parse_workers must be greater than 0".to_string());
+        }
+        if let Some(formats) = &self.enabled_formats
+            && formats.is_empty()
+        {
+            return Err("enabled_formats must list at least one format".to_string());
+        }
+        self.log_filter()?;
+
+        Ok(())
+    }
+
+    pub fn log_filter(&self) -> Result<EnvFilter, String> {
+        EnvFilter::try_new(self.log_level.trim()).map_err(|e| {
+            format!(
+                "log_level {:?} is not a valid filter: {}",
+                self.log_level, e
+            )
+        })
+    }
+
+    pub fn is_format_enabled(&self, format: &str) -> bool {
+        match &self.enabled_formats {
+            Some(formats) => formats.iter().any(|f| f == format),
+            None => true,
+        }
+    }
+}
+
+/// Shape of each log line
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// One JSON object per line, for log collectors
+    Json,
+    /// Human readable lines, for local development
+    Text,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "text" => Ok(Self::Text),
+            _ => Err("expected json or text".to_string()),
+        }
+    }
+}
+
+/// The iCIMS feed is shared with other regions, only Québec jobs are ours
+fn default_allowed_provinces() -> HashMap<String, Vec<String>> {
+    HashMap::from([("xml-icims".to_string(), vec!["QC".to_string()])])
+}
+
+/// `HJ2_ALLOWED_PROVINCES` value, `xml-icims=QC;xml-tidan=QC,ON`
+fn parse_allowed_provinces(value: &str) -> Result<HashMap<String, Vec<String>>, String> {
+    value
+        .split(';')
+        .filter(|entry| !entry.trim().is_empty())
+        .map(|entry| {
+            let (format, provinces) = entry.split_once('=').ok_or_else(|| {
+                format!(
+                    "Invalid value {:?} for HJ2_ALLOWED_PROVINCES: expected format=PROVINCES",
+                    entry
+                )
+            })?;
+            Ok((
+                format.trim().to_string(),
+                provinces
+                    .split(',')
+                    .map(|province| province.trim().to_string())
+                    .collect(),
+            ))
+        })
+        .collect()
+}
+
+fn env_parse<T: FromStr>(name: &str) -> Result<Option<T>, String>
+where
+    T::Err: std::fmt::Display,
+{
+    match std::env::var(name) {
+        Ok(value) => value
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|e| format!("Invalid value {:?} for {}: {}", value, name, e)),
+        Err(_) => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_content(name: &str, content: &str) -> Config {
+        let path = std::env::temp_dir().join(format!("hj2-{}-{}.toml", name, std::process::id()));
+        std::fs::write(&path, content).unwrap();
+        let config = Config::from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        config.unwrap()
+    }
+
+    fn provinces(list: &[&str]) -> Option<Vec<String>> {
+        Some(list.iter().map(|province| province.to_string()).collect())
+    }
+
+    #[test]
+    fn configured_provinces_keep_the_icims_default() {
+        let config = from_content("tidan", "[allowed_provinces]\nxml-tidan = [\"QC\"]\n");
+        assert_eq!(
+            config.allowed_provinces.get("xml-icims").cloned(),
+            provinces(&["QC"])
+        );
+        assert_eq!(
+            config.allowed_provinces.get("xml-tidan").cloned(),
+            provinces(&["QC"])
+        );
+    }
+
+    #[test]
+    fn naming_icims_replaces_its_default() {
+        let config = from_content("icims", "[allowed_provinces]\nxml-icims = [\"*\"]\n");
+        assert_eq!(
+            config.allowed_provinces.get("xml-icims").cloned(),
+            provinces(&["*"])
+        );
+    }
+
+    #[test]
+    fn environment_value_lists_provinces_per_format() {
+        let parsed = parse_allowed_provinces("xml-tidan=QC, ON;").unwrap();
+        assert_eq!(parsed.get("xml-tidan").cloned(), provinces(&["QC", "ON"]));
+        assert!(!parsed.contains_key("xml-icims"));
+        assert!(parse_allowed_provinces("xml-tidan").is_err());
+    }
+}
@@ -1,17 +1,48 @@
-use log::{Record, Level, Metadata};
+use tracing_appender::{non_blocking::WorkerGuard, rolling};
+use tracing_subscriber::{Layer, fmt, layer::SubscriberExt, util::SubscriberInitExt};
 
-pub struct Logger;
+use crate::config::{Config, LogFormat};
 
-impl log::Log for Logger {
-    fn enabled(&self, metadata: &Metadata) -> bool {
-        metadata.level() <= Level::Info
-    }
+/// Install the global subscriber: stdout, plus a daily rotated `hj2-api.log` file when
+/// `log_dir` is set
+///
+/// The returned guard flushes the file writer on drop, keep it alive for the whole process.
+pub fn init(config: &Config) -> Result<Option<WorkerGuard>, String> {
+    let stdout = layer(config.log_format, std::io::stdout, true);
 
-    fn log(&self, record: &Record) {
-        if self.enabled(record.metadata()) {
-            println!("{} - {}", record.level(), record.args());
+    let (file, guard) = match &config.log_dir {
+        Some(dir) => {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Could not create log_dir {}: {}", dir.display(), e))?;
+            let (writer, guard) =
+                tracing_appender::non_blocking(rolling::daily(dir, "hj2-api.log"));
+            (Some(layer(config.log_format, writer, false)), Some(guard))
         }
-    }
+        None => (None, None),
+    };
+
+    tracing_subscriber::registry()
+        .with(config.log_filter()?)
+        .with(stdout)
+        .with(file)
+        .try_init()
+        .map_err(|e| e.to_string())?;
 
-    fn flush(&self) {}
-}
\ No newline at end of file
+    Ok(guard)
+}
+
+fn layer<S, W>(format: LogFormat, writer: W, ansi: bool) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    W: for<'w> fmt::MakeWriter<'w> + Send + Sync + 'static,
+{
+    match format {
+        LogFormat::Json => fmt::layer()
+            .json()
+            .with_current_span(false)
+            .with_span_list(true)
+            .with_writer(writer)
+            .boxed(),
+        LogFormat::Text => fmt::layer().with_ansi(ansi).with_writer(writer).boxed(),
+    }
+}
@@ -1,29 +1,131 @@
 mod api;
+mod config;
 mod logger;
 mod utils;
 
+use std::sync::Arc;
+
 use axum::{
     Router,
+    extract::DefaultBodyLimit,
+    http::Request,
     routing::{get, post},
 };
-use log::{LevelFilter, info};
-
-use crate::logger::Logger;
+use tower::ServiceBuilder;
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, RequestId, SetRequestIdLayer},
+    trace::TraceLayer,
+};
+use tracing::{error, info, info_span};
 
-static LOGGER: Logger = Logger;
+use crate::{
+    api::AppState,
+    config::Config,
+    utils::{metrics::Metrics, pipeline::Pipeline, registry::Registry, worker_pool::WorkerPool},
+};
 
 #[tokio::main]
 async fn main() {
-    log::set_logger(&LOGGER).unwrap();
-    log::set_max_level(LevelFilter::Info);
+    // The logger depends on the configuration, so configuration errors go straight to stderr
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Invalid configuration: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let _log_guard = match logger::init(&config) {
+        Ok(guard) => guard,
+        Err(e) => {
+            eprintln!("Could not set up logging: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    info!("Starting server");
+    let registry = match Registry::new(&config) {
+        Ok(registry) => registry,
+        Err(e) => {
+            error!("Could not load the feed formats: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let pipeline = match Pipeline::new(&config, &registry.known_formats()) {
+        Ok(pipeline) => pipeline,
+        Err(e) => {
+            error!("Could not set up the import pipeline: {}", e);
+            std::process::exit(1);
+        }
+    };
+    info!(
+        "Schemas loaded from {} ({} formats)",
+        config.schema_dir.display(),
+        registry.parsers().count()
+    );
+
+    // Parsing runs on blocking threads, bounded so a burst of large feeds cannot pile up
+    info!(
+        "Parsing with {} workers and a queue of {}",
+        config.parse_workers, config.parse_queue_limit
+    );
+
+    let metrics = match Metrics::new() {
+        Ok(metrics) => metrics,
+        Err(e) => {
+            error!("Could not register metrics: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let state = AppState {
+        registry: Arc::new(registry),
+        pipeline: Arc::new(pipeline),
+        workers: WorkerPool::new(config.parse_workers, config.parse_queue_limit),
+        metrics,
+        max_upload_bytes: config.max_upload_bytes,
+    };
 
-    info!(target: "main", "Starting server");
     let app: Router = Router::new()
         .route("/", get(api::index::handler))
-        .route("/import", post(api::import::handler));
+        .route("/healthz", get(api::health::healthz))
+        .route("/readyz", get(api::health::readyz))
+        .route("/formats", get(api::formats::handler))
+        .route("/metrics", get(api::metrics::handler))
+        .route("/import", post(api::import::handler))
+        .layer(DefaultBodyLimit::max(config.max_upload_bytes))
+        // Every request gets an x-request-id (kept when the client sends one), attached to
+        // the request span and echoed back in the response
+        .layer(
+            ServiceBuilder::new()
+                .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+                .layer(TraceLayer::new_for_http().make_span_with(request_span))
+                .layer(PropagateRequestIdLayer::x_request_id()),
+        )
+        .with_state(Arc::new(state));
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:80").await.unwrap();
+    let listener = match tokio::net::TcpListener::bind(config.listen_addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Could not listen on {}: {}", config.listen_addr, e);
+            std::process::exit(1);
+        }
+    };
 
-    info!(target: "main", "Server is running on port 80");
+    info!("Server is running on {}", config.listen_addr);
     axum::serve(listener, app).await.unwrap();
 }
+
+fn request_span<B>(request: &Request<B>) -> tracing::Span {
+    let request_id = request
+        .extensions()
+        .get::<RequestId>()
+        .and_then(|id| id.header_value().to_str().ok())
+        .unwrap_or_default();
+
+    info_span!(
+        "request",
+        request_id,
+        method = %request.method(),
+        path = %request.uri().path(),
+    )
+}
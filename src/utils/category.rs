@@ -0,0 +1,234 @@
+use std::{collections::HashMap, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::text;
+
+/// Mapping table shipped with the server, used when `category_map` is not configured
+const DEFAULT_MAP: &str = include_str!("../../mappings/categories.toml");
+
+/// Canonical categories, as enumerated by the HotellerieJobs XSD
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    Hospitality,
+    #[serde(rename = "Food services")]
+    FoodServices,
+    Tourism,
+    #[serde(rename = "Spa and Wellness")]
+    SpaAndWellness,
+    Events,
+    Health,
+    #[serde(rename = "Food Truck")]
+    FoodTruck,
+    Administrative,
+    Outdoors,
+    Other,
+}
+
+impl Category {
+    const ALL: [Category; 10] = [
+        Category::Hospitality,
+        Category::FoodServices,
+        Category::Tourism,
+        Category::SpaAndWellness,
+        Category::Events,
+        Category::Health,
+        Category::FoodTruck,
+        Category::Administrative,
+        Category::Outdoors,
+        Category::Other,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Category::Hospitality => "Hospitality",
+            Category::FoodServices => "Food services",
+            Category::Tourism => "Tourism",
+            Category::SpaAndWellness => "Spa and Wellness",
+            Category::Events => "Events",
+            Category::Health => "Health",
+            Category::FoodTruck => "Food Truck",
+            Category::Administrative => "Administrative",
+            Category::Outdoors => "Outdoors",
+            Category::Other => "Other",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MapFile {
+    #[serde(default)]
+    labels: HashMap<String, Category>,
+    #[serde(default)]
+    formats: HashMap<String, HashMap<String, Category>>,
+}
+
+/// Source label to canonical category lookup, keys are folded with `text::fold`
+pub struct CategoryMap {
+    labels: HashMap<String, Category>,
+    formats: HashMap<String, HashMap<String, Category>>,
+}
+
+impl CategoryMap {
+    /// Load the mapping file, or the built-in table when no path is configured
+    /// `known_formats` guards against typos in `[formats.<key>]` sections
+    pub fn load(path: Option<&Path>, known_formats: &[&str]) -> Result<Self, String> {
+        let (content, origin) = match path {
+            Some(path) => (
+                std::fs::read_to_string(path).map_err(|e| {
+                    format!("Could not read category map {}: {}", path.display(), e)
+                })?,
+                path.display().to_string(),
+            ),
+            None => (DEFAULT_MAP.to_string(), "built-in".to_string()),
+        };
+        let file: MapFile = toml::from_str(&content)
+            .map_err(|e| format!("Invalid category map {}: {}", origin, e))?;
+
+        if let Some(unknown) = file
+            .formats
+            .keys()
+            .find(|format| !known_formats.contains(&format.as_str()))
+        {
+            return Err(format!(
+                "Category map {} has a section for unknown format {}",
+                origin, unknown
+            ));
+        }
+
+        // Canonical names always map to themselves
+        let mut labels: HashMap<String, Category> = Category::ALL
+            .iter()
+            .map(|category| (text::fold(category.label()), *category))
+            .collect();
+        labels.extend(fold_keys(file.labels));
+
+        Ok(Self {
+            labels,
+            formats: file
+                .formats
+                .into_iter()
+                .map(|(format, labels)| (format, fold_keys(labels)))
+                .collect(),
+        })
+    }
+
+    /// Map a raw, possibly comma-separated, category field to canonical categories
+    /// Labels without a mapping become `Other` and are returned as unmapped
+    pub fn map(&self, format: &str, raw: &str) -> (Vec<Category>, Vec<String>) {
+        let mut categories = Vec::new();
+        let mut unmapped = Vec::new();
+
+        for label in raw.split(',').map(|label| label.trim()) {
+            if label.is_empty() {
+                continue;
+            }
+
+            let folded = text::fold(label);
+            let category = self
+                .formats
+                .get(format)
+                .and_then(|labels| labels.get(&folded))
+                .or_else(|| self.labels.get(&folded));
+            let category = match category {
+                Some(category) => *category,
+                None => {
+                    unmapped.push(label.to_string());
+                    Category::Other
+                }
+            };
+
+            if !categories.contains(&category) {
+                categories.push(category);
+            }
+        }
+
+        if categories.is_empty() {
+            categories.push(Category::Other);
+        }
+
+        (categories, unmapped)
+    }
+}
+
+fn fold_keys(labels: HashMap<String, Category>) -> HashMap<String, Category> {
+    labels
+        .into_iter()
+        .map(|(label, category)| (text::fold(&label), category))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAP: &str = r#"
+        [labels]
+        "Cuisine" = "Food services"
+        "Entretien ménager" = "Hospitality"
+
+        [formats.xml-tidan]
+        "Cuisine" = "Food Truck"
+    "#;
+
+    fn load(name: &str, content: &str) -> Result<CategoryMap, String> {
+        let path = std::env::temp_dir().join(format!("hj2-{}-{}.toml", name, std::process::id()));
+        std::fs::write(&path, content).unwrap();
+        let map = CategoryMap::load(Some(&path), &["xml-grandio", "xml-tidan"]);
+        std::fs::remove_file(&path).unwrap();
+        map
+    }
+
+    #[test]
+    fn format_labels_win_over_shared_labels() {
+        let map = load("categories-format", MAP).unwrap();
+        assert_eq!(
+            map.map("xml-tidan", "cuisine"),
+            (vec![Category::FoodTruck], vec![])
+        );
+        assert_eq!(
+            map.map("xml-grandio", "Cuisine"),
+            (vec![Category::FoodServices], vec![])
+        );
+    }
+
+    #[test]
+    fn empty_labels_between_commas_are_skipped() {
+        let map = load("categories-empty", MAP).unwrap();
+        assert_eq!(
+            map.map("xml-tidan", "Entretien menager,,Tourism"),
+            (vec![Category::Hospitality, Category::Tourism], vec![])
+        );
+        assert_eq!(map.map("xml-tidan", ",,"), (vec![Category::Other], vec![]));
+    }
+
+    #[test]
+    fn unknown_labels_become_other_and_are_reported() {
+        let map = load("categories-other", MAP).unwrap();
+        assert_eq!(
+            map.map("xml-grandio", "Plomberie, Cuisine, Électricité"),
+            (
+                vec![Category::Other, Category::FoodServices],
+                vec!["Plomberie".to_string(), "Électricité".to_string()]
+            )
+        );
+    }
+
+    #[test]
+    fn sections_of_unknown_formats_are_rejected() {
+        let error = load(
+            "categories-unknown",
+            "[formats.xml-unknown]\n\"Cuisine\" = \"Food services\"\n",
+        )
+        .err()
+        .unwrap();
+        assert!(error.contains("unknown format xml-unknown"), "{}", error);
+    }
+
+    #[test]
+    fn built_in_map_loads() {
+        let map = CategoryMap::load(None, &["xml-grandio", "xml-tidan"]);
+        assert!(map.is_ok(), "{:?}", map.err());
+    }
+}
@@ -4,7 +4,12 @@ use libxml::schemas;
 use libxml::tree::Document;
 use serde::Serialize;
 
-#[derive(Serialize)]
+use crate::utils::{
+    category::Category, duplicates::DuplicateKind, employment::EmploymentType,
+    language::LanguageSource, validation::Severity,
+};
+
+#[derive(Serialize, Default)]
 pub struct Company {
     pub id: String,
     pub name: String,
@@ -13,50 +18,216 @@ pub struct Company {
     pub logo_url: String,
 }
 
-impl Default for Company {
-    fn default() -> Self {
-        Self {
-            id: String::new(),
-            name: String::new(),
-            city: String::new(),
-            postal_code: String::new(),
-            logo_url: String::new(),
+#[derive(Serialize, Default, Clone, Debug)]
+pub struct Location {
+    pub city: String,
+    pub province: String,
+    pub postal_code: String,
+    pub street_address: String,
+    pub country: String,
+}
+
+impl Location {
+    /// One location per city of a comma-separated city field, every city sharing the rest of
+    /// `address`
+    pub fn split_cities(cities: &str, address: Location) -> Vec<Location> {
+        let locations: Vec<Location> = cities
+            .split(',')
+            .map(|city| city.trim())
+            .filter(|city| !city.is_empty())
+            .map(|city| Location {
+                city: city.to_string(),
+                ..address.clone()
+            })
+            .collect();
+
+        if !locations.is_empty() {
+            return locations;
         }
+
+        // No city but still part of an address, keep what the feed gave
+        let has_address = [
+            &address.province,
+            &address.postal_code,
+            &address.street_address,
+            &address.country,
+        ]
+        .iter()
+        .any(|part| !part.trim().is_empty());
+        if has_address { vec![address] } else { vec![] }
     }
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Default)]
 pub struct Translation {
     pub language: String,
+    /// How `language` was decided, filled by the import pipeline for unlabeled translations
+    pub language_source: LanguageSource,
+    /// 1 when the feed labels the language, the detection score otherwise
+    pub language_confidence: f32,
     pub title: String,
+    /// Sanitized HTML once through the import pipeline
     pub description: String,
+    pub description_text: String,
+    pub description_markdown: String,
+    /// Sanitized HTML once through the import pipeline
     pub requirements: String,
+    pub requirements_text: String,
+    pub requirements_markdown: String,
 }
 
-impl Default for Translation {
-    fn default() -> Self {
-        Self {
-            language: String::new(),
-            title: String::new(),
-            description: String::new(),
-            requirements: String::new(),
-        }
-    }
-}
-
-#[derive(Serialize)]
+#[derive(Serialize, Default)]
 pub struct Job {
     pub id: String,
+    /// Schedule as written in the feed
     pub schedule: String,
+    /// `schedule` normalized by the import pipeline
+    pub employment_types: Vec<EmploymentType>,
+    /// Category as written in the feed
     pub category: String,
+    /// `category` mapped to the canonical list by the import pipeline
+    pub categories: Vec<Category>,
+    /// City field as written in the feed, kept for compatibility, see `locations`
     pub city: String,
+    /// ISO 3166-2:CA code ("CA-QC") once normalized, or the feed value when unrecognized
     pub province: String,
+    /// Every place the job is offered, one per city of multi-location postings
+    pub locations: Vec<Location>,
     pub application_method: String,
     pub application_destination: String,
     pub company: Company,
     pub translations: Vec<Translation>,
 }
 
+/// A problem with one job that did not stop the import
+#[derive(Serialize, Debug)]
+pub struct Warning {
+    pub job_id: String,
+    pub field: &'static str,
+    pub message: String,
+}
+
+/// A job left out of the import on purpose
+#[derive(Serialize, Debug)]
+pub struct SkippedJob {
+    pub job_id: String,
+    pub reason: String,
+}
+
+/// A shared field that differs between records merged into one job
+#[derive(Serialize, Debug)]
+pub struct MergeConflict {
+    pub job_id: String,
+    pub field: &'static str,
+    /// Value of the first record, used by the merged job
+    pub kept: String,
+    pub ignored: String,
+}
+
+/// A job that breaks a platform rule, jobs with an error are left out of the import
+#[derive(Serialize, Debug)]
+pub struct ValidationIssue {
+    pub job_id: String,
+    pub severity: Severity,
+    pub field: &'static str,
+    pub message: String,
+}
+
+/// A job record excluded by a lenient import because it does not match the schema
+#[derive(Serialize, Debug)]
+pub struct RejectedJob {
+    /// Id found in the record, empty when the record has none
+    pub job_id: String,
+    /// Line where the record starts
+    pub line: i32,
+    pub errors: Vec<XMLError>,
+}
+
+/// A job left out because the feed already had it
+#[derive(Serialize, Debug)]
+pub struct Duplicate {
+    pub job_id: String,
+    /// Id of the job kept in its place
+    pub duplicate_of: String,
+    pub kind: DuplicateKind,
+    /// Title of the left out record, to tell records sharing an id apart
+    pub title: String,
+}
+
+/// A source value the pipeline had no mapping for, and how many jobs used it
+#[derive(Serialize, Debug)]
+pub struct UnmappedLabel {
+    pub label: String,
+    pub jobs: usize,
+}
+
+impl UnmappedLabel {
+    /// Add one occurrence of `label` to a report
+    pub fn count(report: &mut Vec<UnmappedLabel>, label: String) {
+        match report.iter_mut().find(|entry| entry.label == label) {
+            Some(entry) => entry.jobs += 1,
+            None => report.push(UnmappedLabel { label, jobs: 1 }),
+        }
+    }
+}
+
+/// A field label of the feed and the mapping key it was looked up as
+#[derive(Serialize, Debug)]
+pub struct LabelMatch {
+    /// Label as written in the feed
+    pub label: String,
+    /// Label as written in the format mapping
+    pub key: String,
+    /// Job fields it fills
+    pub fields: Vec<String>,
+    pub jobs: usize,
+}
+
+/// How the labels of a feed keying its fields by label matched the format mapping
+#[derive(Default, Debug)]
+pub struct LabelReport {
+    pub matched: Vec<LabelMatch>,
+    /// Labels of the feed no mapping key stands for
+    pub unused: Vec<UnmappedLabel>,
+}
+
+/// A source field and how many times the job records have it
+#[derive(Serialize, Debug)]
+pub struct FieldCount {
+    pub field: String,
+    pub occurrences: usize,
+}
+
+/// Source fields of the job records of a feed, to spot data a client sends that is not
+/// imported
+#[derive(Serialize, Debug, Default)]
+pub struct FieldReport {
+    pub format: String,
+    /// Every field name found, sorted
+    pub seen: Vec<String>,
+    /// Fields the format mapping reads
+    pub mapped: Vec<FieldCount>,
+    /// Fields the format mapping leaves out
+    pub ignored: Vec<FieldCount>,
+}
+
+/// Jobs mapped from a feed, with what the pipeline noticed along the way
+#[derive(Default)]
+pub struct ParsedFeed {
+    pub jobs: Vec<Job>,
+    pub warnings: Vec<Warning>,
+    pub skipped: Vec<SkippedJob>,
+    pub unmapped_categories: Vec<UnmappedLabel>,
+    pub merge_conflicts: Vec<MergeConflict>,
+    pub duplicates: Vec<Duplicate>,
+    pub validation_issues: Vec<ValidationIssue>,
+    /// Filled by the parser in lenient mode, before the pipeline runs
+    pub rejected_jobs: Vec<RejectedJob>,
+    /// Filled by the parser, before the pipeline runs
+    pub labels: LabelReport,
+    pub source_fields: Option<FieldReport>,
+}
+
 #[derive(Serialize, Debug)]
 pub struct XMLError {
     pub line: i32,
@@ -67,24 +238,103 @@ pub struct XMLError {
     pub code: i32,
 }
 
+/// What stage of the import rejected the request
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ParseErrorKind {
+    /// The upload is not a well-formed XML document
+    NotWellFormed,
+    /// The document does not match the format XSD
+    SchemaInvalid,
+    /// The document is valid but could not be mapped into jobs
+    MappingFailed,
+    /// The multipart body could not be read or misses a field
+    MultipartInvalid,
+    /// The body is larger than `max_upload_bytes`
+    PayloadTooLarge,
+    /// The format or its profile is unknown, or the format could not be detected
+    UnsupportedFormat,
+    /// The format is known but cannot be imported yet
+    NotImplemented,
+    /// The feed has duplicate jobs and the request asked to reject it
+    DuplicateJobs,
+    /// Too many imports are already waiting to be parsed
+    Overloaded,
+    /// Something went wrong on our side
+    Internal,
+}
+
+impl ParseErrorKind {
+    /// Same key as the serialized `error_kind`, for logs
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::NotWellFormed => "not-well-formed",
+            Self::SchemaInvalid => "schema-invalid",
+            Self::MappingFailed => "mapping-failed",
+            Self::MultipartInvalid => "multipart-invalid",
+            Self::PayloadTooLarge => "payload-too-large",
+            Self::UnsupportedFormat => "unsupported-format",
+            Self::NotImplemented => "not-implemented",
+            Self::DuplicateJobs => "duplicate-jobs",
+            Self::Overloaded => "overloaded",
+            Self::Internal => "internal",
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct ParseError {
+    pub kind: ParseErrorKind,
     pub message: String,
     pub xml_errors: Vec<XMLError>,
 }
 
-pub fn validate_against_xsd(file: &Bytes, xsd_path: &str) -> Result<Document, Vec<XMLError>> {
-    let mut schema_parser = schemas::SchemaParserContext::from_file(xsd_path);
-    let mut schema_validation =
-        schemas::SchemaValidationContext::from_parser(&mut schema_parser).unwrap();
+impl ParseError {
+    pub fn new(kind: ParseErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            xml_errors: vec![],
+        }
+    }
+}
+
+/// Parse the upload without libxml2 recovery, so malformed input is rejected instead of patched
+pub fn parse_document(file: &Bytes) -> Result<Document, ParseError> {
+    let options = parser::ParserOptions {
+        recover: false,
+        no_net: true,
+        ..Default::default()
+    };
 
-    let parser = parser::Parser::default();
-    let document = parser.parse_string(file).unwrap();
+    let document = parser::Parser::default()
+        .parse_string_with_options(file, options)
+        .map_err(|e| {
+            let message = match e {
+                parser::XmlParseError::DocumentTooLarge => "File is too large to be parsed",
+                _ => "File is not well-formed XML",
+            };
+            ParseError::new(ParseErrorKind::NotWellFormed, message)
+        })?;
+
+    if document.get_root_element().is_none() {
+        return Err(ParseError::new(
+            ParseErrorKind::NotWellFormed,
+            "File has no root element",
+        ));
+    }
+
+    Ok(document)
+}
 
-    match schema_validation.validate_document(&document) {
-        Ok(_) => Ok(document),
+pub fn validate_against_xsd(
+    document: &Document,
+    schema_validation: &mut schemas::SchemaValidationContext,
+) -> Result<(), ParseError> {
+    match schema_validation.validate_document(document) {
+        Ok(_) => Ok(()),
         Err(e) => {
-            println!("Error: {:?}", e);
+            tracing::debug!(errors = e.len(), "Document does not match the schema");
 
             let errors = e
                 .iter()
@@ -98,7 +348,11 @@ pub fn validate_against_xsd(file: &Bytes, xsd_path: &str) -> Result<Document, Ve
                 })
                 .collect();
 
-            Err(errors)
+            Err(ParseError {
+                kind: ParseErrorKind::SchemaInvalid,
+                message: "File is not valid".to_string(),
+                xml_errors: errors,
+            })
         }
     }
 }
@@ -0,0 +1,200 @@
+use std::cmp::Reverse;
+
+use axum::body::Bytes;
+use libxml::tree::Node;
+use serde::Serialize;
+
+use crate::utils::{
+    common::{self, ParseError, ParseErrorKind},
+    registry::Registry,
+};
+
+// A sniff score at or above this value, well ahead of the runner-up, is trusted without validation
+const TRUSTED_SCORE: u32 = 80;
+const TRUSTED_MARGIN: u32 = 30;
+
+#[derive(Serialize)]
+pub struct Candidate {
+    pub format: String,
+    pub score: u32,
+    pub schema_valid: Option<bool>,
+}
+
+#[derive(Serialize)]
+pub struct Detection {
+    pub format: String,
+    pub confidence: f32,
+    pub ambiguous: bool,
+    pub candidates: Vec<Candidate>,
+}
+
+/// Whether the xpath, relative to `node`, matches at least one node
+pub fn has_nodes(node: &Node, xpath: &str) -> bool {
+    node.findnodes(xpath)
+        .map(|nodes| !nodes.is_empty())
+        .unwrap_or(false)
+}
+
+/// Guess the format of a feed from its shape, validating against the candidate schemas when
+/// the shape alone is not conclusive
+pub fn detect(registry: &Registry, file: &Bytes) -> Result<Detection, ParseError> {
+    let document = common::parse_document(file)?;
+    // parse_document guarantees a root element
+    let root = document.get_root_element().unwrap();
+
+    let mut candidates: Vec<Candidate> = registry
+        .parsers()
+        .filter(|parser| parser.is_implemented())
+        .map(|parser| Candidate {
+            format: parser.name().to_string(),
+            score: parser.sniff(&root).min(100),
+            schema_valid: None,
+        })
+        .filter(|candidate| candidate.score > 0)
+        .collect();
+    candidates.sort_by_key(|candidate| Reverse(candidate.score));
+
+    let top_score = candidates.first().map(|c| c.score).unwrap_or(0);
+    let second_score = candidates.get(1).map(|c| c.score).unwrap_or(0);
+
+    if top_score >= TRUSTED_SCORE && top_score - second_score >= TRUSTED_MARGIN {
+        return Ok(Detection {
+            format: candidates[0].format.clone(),
+            confidence: top_score as f32 / 100.0,
+            ambiguous: false,
+            candidates,
+        });
+    }
+
+    // Nothing in the shape looked familiar, fall back to trying every schema
+    if candidates.is_empty() {
+        candidates = registry
+            .parsers()
+            .filter(|parser| parser.is_implemented())
+            .map(|parser| Candidate {
+                format: parser.name().to_string(),
+                score: 0,
+                schema_valid: None,
+            })
+            .collect();
+    }
+
+    for candidate in candidates.iter_mut() {
+        candidate.schema_valid = Some(registry.schemas().validate(file, &candidate.format).is_ok());
+    }
+
+    let valid: Vec<&Candidate> = candidates
+        .iter()
+        .filter(|c| c.schema_valid == Some(true))
+        .collect();
+
+    let (format, confidence, ambiguous) = match valid.as_slice() {
+        [] => {
+            let Some(best) = candidates.iter().find(|c| c.score > 0) else {
+                return Err(ParseError::new(
+                    ParseErrorKind::UnsupportedFormat,
+                    "Could not detect the format of the file",
+                ));
+            };
+            // Keep the closest shape so the import reports its schema errors
+            (
+                best.format.clone(),
+                best.score as f32 / 200.0,
+                candidates.len() > 1,
+            )
+        }
+        [only] => (
+            only.format.clone(),
+            (only.score as f32 / 100.0 + 1.0) / 2.0,
+            false,
+        ),
+        [best, ..] => {
+            let total: u32 = valid.iter().map(|c| c.score.max(1)).sum();
+            (
+                best.format.clone(),
+                best.score.max(1) as f32 / total as f32,
+                true,
+            )
+        }
+    };
+
+    Ok(Detection {
+        format,
+        confidence,
+        ambiguous,
+        candidates,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::config::Config;
+
+    fn registry() -> Registry {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        Registry::new(&Config {
+            schema_dir: root.join("xsd-schemas"),
+            mapping_dir: root.join("mappings/formats"),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn detect_xml(xml: &str) -> Result<Detection, ParseError> {
+        detect(&registry(), &Bytes::from(xml.to_string()))
+    }
+
+    fn scores(detection: &Detection) -> Vec<(&str, u32, Option<bool>)> {
+        detection
+            .candidates
+            .iter()
+            .map(|c| (c.format.as_str(), c.score, c.schema_valid))
+            .collect()
+    }
+
+    #[test]
+    fn trusted_sniff_skips_validation() {
+        let detection =
+            detect_xml("<source><job><unique_id>HJ-1</unique_id></job></source>").unwrap();
+
+        assert_eq!(detection.format, "xml-hotelleriejobs");
+        assert_eq!(detection.confidence, 0.9);
+        assert!(!detection.ambiguous);
+        assert_eq!(scores(&detection), [("xml-hotelleriejobs", 90, None)]);
+    }
+
+    #[test]
+    fn ambiguous_sniff_is_resolved_by_the_schemas() {
+        // Grandio and Tidan share the shape of records without their distinguishing fields
+        let detection = detect_xml(
+            "<source><job><businessProcessId>G-77-fr</businessProcessId>\
+             <description>Serveur</description><applyUrl>https://grandio.example/77</applyUrl>\
+             <jobCode>G77FR</jobCode>\
+             <applicationFormUrl>https://grandio.example/77/form</applicationFormUrl>\
+             </job></source>",
+        )
+        .unwrap();
+
+        assert_eq!(detection.format, "xml-grandio");
+        assert_eq!(detection.confidence, 0.7);
+        assert!(!detection.ambiguous);
+        assert_eq!(
+            scores(&detection),
+            [
+                ("xml-grandio", 40, Some(true)),
+                ("xml-tidan", 40, Some(false))
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_document_is_unsupported() {
+        let error = detect_xml("<catalog><item>1</item></catalog>")
+            .err()
+            .unwrap();
+        assert_eq!(error.kind, ParseErrorKind::UnsupportedFormat);
+    }
+}
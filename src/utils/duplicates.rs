@@ -0,0 +1,228 @@
+use std::{collections::HashMap, str::FromStr};
+
+use serde::Serialize;
+
+use crate::utils::{
+    common::{Duplicate, Job},
+    text,
+};
+
+/// What to do with jobs appearing more than once in a feed, chosen per request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    /// Keep the first record, report the others
+    #[default]
+    KeepFirst,
+    /// Keep the last record, feeds appending updates put the latest one at the end
+    KeepLast,
+    /// Fail the import and list the duplicates
+    Reject,
+}
+
+impl FromStr for DuplicatePolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "keep-first" => Ok(Self::KeepFirst),
+            "keep-last" => Ok(Self::KeepLast),
+            "reject" => Ok(Self::Reject),
+            _ => Err("expected keep-first, keep-last or reject".to_string()),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum DuplicateKind {
+    /// Same `id`
+    SameId,
+    /// Different ids but the same title, company and city once normalized
+    NearDuplicate,
+}
+
+/// Drop the jobs already seen in the feed, reporting each of them
+/// `near` also compares title, company and city, reposted jobs often get a new id
+pub fn remove(
+    jobs: Vec<Job>,
+    policy: DuplicatePolicy,
+    near: bool,
+    report: &mut Vec<Duplicate>,
+) -> Vec<Job> {
+    let keep_last = policy == DuplicatePolicy::KeepLast;
+    let mut ordered = jobs;
+    if keep_last {
+        ordered.reverse();
+    }
+
+    let mut ids: HashMap<String, String> = HashMap::new();
+    let mut signatures: HashMap<String, String> = HashMap::new();
+    let mut kept = Vec::with_capacity(ordered.len());
+    let mut found = Vec::new();
+
+    for job in ordered {
+        let id = job.id.trim().to_string();
+        // Jobs without an id cannot be told apart by it
+        let same_id = (!id.is_empty()).then(|| ids.get(&id)).flatten();
+        let signature = if near { signature(&job) } else { None };
+        let same_signature = signature
+            .as_ref()
+            .and_then(|signature| signatures.get(signature));
+
+        let duplicate = match (same_id, same_signature) {
+            (Some(original), _) => Some((original.clone(), DuplicateKind::SameId)),
+            (None, Some(original)) => Some((original.clone(), DuplicateKind::NearDuplicate)),
+            (None, None) => None,
+        };
+        if let Some((duplicate_of, kind)) = duplicate {
+            found.push(Duplicate {
+                title: title(&job).to_string(),
+                job_id: job.id,
+                duplicate_of,
+                kind,
+            });
+            continue;
+        }
+
+        if !id.is_empty() {
+            ids.insert(id, job.id.clone());
+        }
+        if let Some(signature) = signature {
+            signatures.insert(signature, job.id.clone());
+        }
+        kept.push(job);
+    }
+
+    if keep_last {
+        kept.reverse();
+        found.reverse();
+    }
+    report.extend(found);
+
+    kept
+}
+
+fn title(job: &Job) -> &str {
+    job.translations
+        .iter()
+        .map(|translation| translation.title.trim())
+        .find(|title| !title.is_empty())
+        .unwrap_or_default()
+}
+
+/// Normalized title, company and city, `None` without a title to compare
+fn signature(job: &Job) -> Option<String> {
+    let title = text::fold(title(job));
+    if title.is_empty() {
+        return None;
+    }
+
+    Some(format!(
+        "{}|{}|{}",
+        title,
+        text::fold(&job.company.name),
+        text::fold(&job.city)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::common::{Company, Translation};
+
+    fn job(id: &str, title: &str) -> Job {
+        Job {
+            id: id.to_string(),
+            city: "Québec".to_string(),
+            company: Company {
+                name: "Résidence XYZ".to_string(),
+                ..Default::default()
+            },
+            translations: vec![Translation {
+                title: title.to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn titles(jobs: &[Job]) -> Vec<&str> {
+        jobs.iter().map(title).collect()
+    }
+
+    #[test]
+    fn keep_first_reports_later_records() {
+        let mut report = vec![];
+        let kept = remove(
+            vec![
+                job("1", "Serveur"),
+                job("2", "Cuisinier"),
+                job("1", "Plongeur"),
+            ],
+            DuplicatePolicy::KeepFirst,
+            false,
+            &mut report,
+        );
+
+        assert_eq!(titles(&kept), ["Serveur", "Cuisinier"]);
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].title, "Plongeur");
+        assert_eq!(report[0].kind, DuplicateKind::SameId);
+    }
+
+    #[test]
+    fn keep_last_keeps_the_latest_record_in_feed_order() {
+        let mut report = vec![];
+        let kept = remove(
+            vec![
+                job("1", "Serveur"),
+                job("2", "Cuisinier"),
+                job("1", "Plongeur"),
+            ],
+            DuplicatePolicy::KeepLast,
+            false,
+            &mut report,
+        );
+
+        assert_eq!(titles(&kept), ["Cuisinier", "Plongeur"]);
+        assert_eq!(report[0].title, "Serveur");
+    }
+
+    #[test]
+    fn near_duplicates_compare_normalized_title_company_and_city() {
+        let mut report = vec![];
+        let kept = remove(
+            vec![
+                job("1", "Préposé aux bénéficiaires"),
+                job("2", "PREPOSE aux beneficiaires"),
+            ],
+            DuplicatePolicy::KeepFirst,
+            true,
+            &mut report,
+        );
+
+        assert_eq!(kept.len(), 1);
+        assert_eq!(report[0].kind, DuplicateKind::NearDuplicate);
+        assert_eq!(report[0].duplicate_of, "1");
+    }
+
+    #[test]
+    fn jobs_without_id_are_not_duplicates_of_each_other() {
+        let mut report = vec![];
+        let kept = remove(
+            vec![job("", "Serveur"), job(" ", "Cuisinier")],
+            DuplicatePolicy::Reject,
+            false,
+            &mut report,
+        );
+
+        assert_eq!(kept.len(), 2);
+        assert!(report.is_empty());
+    }
+
+    #[test]
+    fn policy_parses_request_values() {
+        assert_eq!(" Keep-Last ".parse(), Ok(DuplicatePolicy::KeepLast));
+        assert!("drop".parse::<DuplicatePolicy>().is_err());
+    }
+}
@@ -0,0 +1,133 @@
+use serde::Serialize;
+
+use crate::utils::text;
+
+/// Canonical employment type, whatever wording the feed used
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum EmploymentType {
+    FullTime,
+    PartTime,
+    Permanent,
+    Temporary,
+    Seasonal,
+    Casual,
+    Internship,
+    Freelance,
+}
+
+/// Folded spellings (see `text::fold`) of every recognized value, French and English
+const VARIANTS: &[(&str, EmploymentType)] = &[
+    ("temps plein", EmploymentType::FullTime),
+    ("plein temps", EmploymentType::FullTime),
+    ("temps complet", EmploymentType::FullTime),
+    ("full time", EmploymentType::FullTime),
+    ("fulltime", EmploymentType::FullTime),
+    ("temps partiel", EmploymentType::PartTime),
+    ("partiel", EmploymentType::PartTime),
+    ("part time", EmploymentType::PartTime),
+    ("parttime", EmploymentType::PartTime),
+    ("permanent", EmploymentType::Permanent),
+    ("permanente", EmploymentType::Permanent),
+    ("regulier", EmploymentType::Permanent),
+    ("reguliere", EmploymentType::Permanent),
+    ("regular", EmploymentType::Permanent),
+    ("temporaire", EmploymentType::Temporary),
+    ("temporary", EmploymentType::Temporary),
+    ("contractuel", EmploymentType::Temporary),
+    ("contrat", EmploymentType::Temporary),
+    ("contract", EmploymentType::Temporary),
+    ("remplacement", EmploymentType::Temporary),
+    ("saisonnier", EmploymentType::Seasonal),
+    ("saisonniere", EmploymentType::Seasonal),
+    ("seasonal", EmploymentType::Seasonal),
+    ("emploi d ete", EmploymentType::Seasonal),
+    ("summer job", EmploymentType::Seasonal),
+    ("occasionnel", EmploymentType::Casual),
+    ("occasionnelle", EmploymentType::Casual),
+    ("sur appel", EmploymentType::Casual),
+    ("casual", EmploymentType::Casual),
+    ("on call", EmploymentType::Casual),
+    ("stage", EmploymentType::Internship),
+    ("stagiaire", EmploymentType::Internship),
+    ("internship", EmploymentType::Internship),
+    ("intern", EmploymentType::Internship),
+    ("co op", EmploymentType::Internship),
+    ("coop", EmploymentType::Internship),
+    ("pigiste", EmploymentType::Freelance),
+    ("freelance", EmploymentType::Freelance),
+];
+
+/// Words and separators feeds use to list several schedules in one field
+const SEPARATORS: &[&str] = &[
+    ",", ";", "/", "|", "&", "+", " - ", " et ", " and ", " ou ", " or ",
+];
+
+/// Map a raw schedule ("Temps-plein et Saisonnier", "Full-time - Permanent") to employment
+/// types, returning the tokens that did not match any known variant
+pub fn normalize(raw: &str) -> (Vec<EmploymentType>, Vec<String>) {
+    let mut tokens = vec![raw.to_string()];
+    for separator in SEPARATORS {
+        tokens = tokens
+            .iter()
+            .flat_map(|token| token.split(separator))
+            .map(|token| token.to_string())
+            .collect();
+    }
+
+    let mut types = Vec::new();
+    let mut unrecognized = Vec::new();
+    for token in tokens.iter().map(|token| token.trim()) {
+        if token.is_empty() {
+            continue;
+        }
+
+        let folded = text::fold(token);
+        match VARIANTS.iter().find(|(variant, _)| *variant == folded) {
+            Some((_, employment_type)) => {
+                if !types.contains(employment_type) {
+                    types.push(*employment_type);
+                }
+            }
+            None => unrecognized.push(token.to_string()),
+        }
+    }
+
+    (types, unrecognized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use EmploymentType::*;
+
+    #[test]
+    fn french_schedules_are_split_on_words() {
+        assert_eq!(
+            normalize("Temps-plein et Saisonnier"),
+            (vec![FullTime, Seasonal], vec![])
+        );
+    }
+
+    #[test]
+    fn english_schedules_are_split_on_dashes() {
+        assert_eq!(
+            normalize("Full-time - Permanent"),
+            (vec![FullTime, Permanent], vec![])
+        );
+    }
+
+    #[test]
+    fn bilingual_schedules_give_one_type() {
+        assert_eq!(normalize("Stage / Internship"), (vec![Internship], vec![]));
+    }
+
+    #[test]
+    fn unrecognized_tokens_are_returned() {
+        assert_eq!(
+            normalize("Temps partiel, Fin de semaine"),
+            (vec![PartTime], vec!["Fin de semaine".to_string()])
+        );
+        assert_eq!(normalize(" "), (vec![], vec![]));
+    }
+}
@@ -0,0 +1,150 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet, HashMap},
+};
+
+use libxml::tree::{Document, Node};
+
+use crate::utils::{
+    common::{FieldCount, FieldReport},
+    text,
+};
+
+/// Count the source fields of the job records matched by `records`, relative to the root
+/// element, and split them between the ones in `mapped` and the ones the mapping ignores
+///
+/// Fields are the child elements of a record, named by `key_attribute` when set. Such names
+/// are labels written by hand and compared the way the mapping looks them up.
+pub fn report(
+    format: &str,
+    document: &Document,
+    records: &str,
+    key_attribute: Option<&str>,
+    mapped: &[impl AsRef<str>],
+) -> FieldReport {
+    let mut occurrences: BTreeMap<String, usize> = BTreeMap::new();
+    let records = document
+        .get_root_element()
+        .and_then(|root| root.findnodes(records).ok())
+        .unwrap_or_default();
+    for record in records {
+        for child in record.get_child_elements() {
+            let name = match key_attribute {
+                Some(attribute) => match child.get_attribute(attribute) {
+                    Some(name) => name,
+                    None => continue,
+                },
+                None => child.get_name(),
+            };
+            *occurrences.entry(name).or_default() += 1;
+        }
+    }
+
+    let is_mapped = |name: &str| match key_attribute {
+        Some(_) => {
+            let key = text::label_key(name);
+            mapped
+                .iter()
+                .any(|field| text::label_key(field.as_ref()) == key)
+        }
+        None => mapped.iter().any(|field| field.as_ref() == name),
+    };
+
+    let mut report = FieldReport {
+        format: format.to_string(),
+        ..Default::default()
+    };
+    for (field, occurrences) in occurrences {
+        report.seen.push(field.clone());
+        let count = FieldCount { field, occurrences };
+        if is_mapped(&count.field) {
+            report.mapped.push(count);
+        } else {
+            report.ignored.push(count);
+        }
+    }
+
+    report
+}
+
+/// Children of a job record keyed by element name, remembering the names the mapping reads
+/// so the field report derives the mapped fields from the lookups themselves
+#[derive(Default)]
+pub struct Record<'a> {
+    node: Option<&'a Node>,
+    values: HashMap<String, String>,
+    read: RefCell<BTreeSet<String>>,
+}
+
+impl<'a> Record<'a> {
+    /// Repeated children are overwritten, the last one wins
+    pub fn new(node: &'a Node) -> Self {
+        let mut values = HashMap::new();
+        let mut child = node.get_first_child();
+        while let Some(current_child) = child {
+            values.insert(current_child.get_name(), current_child.get_content());
+            child = current_child.get_next_sibling();
+        }
+
+        Self {
+            node: Some(node),
+            values,
+            ..Default::default()
+        }
+    }
+
+    /// Content of the child named `name`, empty without one
+    pub fn get(&self, name: &str) -> String {
+        self.read.borrow_mut().insert(name.to_string());
+        self.values.get(name).cloned().unwrap_or_default()
+    }
+
+    /// Nodes at `xpath` below the record, the child its first step names counts as read
+    pub fn find(&self, xpath: &str) -> Result<Vec<Node>, String> {
+        let child = xpath.split('/').next().unwrap_or(xpath);
+        self.read.borrow_mut().insert(child.to_string());
+        match self.node {
+            Some(node) => node
+                .findnodes(xpath)
+                .map_err(|e| format!("Error finding {} nodes: {:?}", xpath, e)),
+            None => Ok(vec![]),
+        }
+    }
+}
+
+/// Names of the children `map` reads, found by running it over an empty record
+/// Mappings must look their fields up whatever the values they find, see
+/// `FeedParser::field_report`. The mapped value is dropped, an empty record has no node to
+/// query so lookups cannot fail.
+pub fn mapped_by<T>(map: impl FnOnce(&Record) -> T) -> Vec<String> {
+    let record = Record::default();
+    map(&record);
+    record.read.into_inner().into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mapped_by_lists_the_children_read() {
+        let mapped = mapped_by(|record| {
+            record.get("title");
+            record.find("location/city").unwrap();
+            record.get("title")
+        });
+        assert_eq!(mapped, ["location", "title"]);
+    }
+
+    #[test]
+    fn record_reads_the_last_repeated_child() {
+        let document = libxml::parser::Parser::default()
+            .parse_string("<job><id>1</id><city>Laval</city><city>Lévis</city></job>")
+            .unwrap();
+        let job = document.get_root_element().unwrap();
+        let record = Record::new(&job);
+        assert_eq!(record.get("city"), "Lévis");
+        assert_eq!(record.get("province"), "");
+        assert_eq!(record.find("city").unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,522 @@
+use libxml::{
+    parser::{Parser, ParserOptions},
+    tree::{Document, Node, NodeType},
+};
+
+/// A feed description cleaned up for the three ways consumers display it
+#[derive(Default)]
+pub struct Rendition {
+    /// Whitelisted tags only, no attributes except safe link targets
+    pub html: String,
+    pub text: String,
+    pub markdown: String,
+}
+
+/// Dropped with everything inside them
+const DROPPED: &[&str] = &[
+    "script", "style", "iframe", "object", "embed", "form", "input", "button", "select",
+    "textarea", "noscript", "template", "head", "title", "meta", "link", "svg", "canvas",
+];
+
+/// Kept in the sanitized HTML, other elements are replaced by their content
+const ALLOWED: &[&str] = &[
+    "p",
+    "div",
+    "br",
+    "ul",
+    "ol",
+    "li",
+    "strong",
+    "b",
+    "em",
+    "i",
+    "u",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "a",
+    "blockquote",
+];
+
+/// Start a new paragraph in the text and Markdown renditions
+const BLOCKS: &[&str] = &[
+    "p",
+    "div",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "blockquote",
+    "table",
+    "tr",
+    "section",
+    "article",
+    "header",
+    "footer",
+    "ul",
+    "ol",
+];
+
+/// Sanitize a description or requirements field and render it as text and Markdown
+pub fn sanitize(raw: &str) -> Rendition {
+    let mut source = fix_encoding(raw.trim());
+
+    // Some feeds escape their HTML twice, the XML parser only undoes one level
+    for _ in 0..2 {
+        if source.contains('<') || !source.contains("&lt;") {
+            break;
+        }
+        source = decode_entities(&source);
+    }
+
+    if source.trim().is_empty() {
+        return Rendition::default();
+    }
+
+    let Some(document) = parse_html(&source) else {
+        return Rendition::default();
+    };
+    let Some(body) = body(&document) else {
+        return Rendition::default();
+    };
+
+    let mut renderer = Renderer::default();
+    renderer.children(&body);
+
+    Rendition {
+        html: renderer.html.trim().to_string(),
+        text: tidy(&renderer.text),
+        markdown: tidy(&renderer.markdown),
+    }
+}
+
+fn parse_html(html: &str) -> Option<Document> {
+    let options = ParserOptions {
+        no_net: true,
+        encoding: Some("UTF-8"),
+        ..Default::default()
+    };
+
+    Parser::default_html()
+        .parse_string_with_options(format!("<html><body>{}</body></html>", html), options)
+        .ok()
+}
+
+/// The document must outlive the returned node
+fn body(document: &Document) -> Option<Node> {
+    document
+        .get_root_element()?
+        .get_child_nodes()
+        .into_iter()
+        .find(|node| node.get_name().eq_ignore_ascii_case("body"))
+}
+
+/// Turn `&lt;p&gt;` back into `<p>` by letting the HTML parser resolve the entities
+fn decode_entities(escaped: &str) -> String {
+    parse_html(escaped)
+        .and_then(|document| body(&document).map(|body| body.get_content()))
+        .unwrap_or_else(|| escaped.to_string())
+}
+
+/// Undo UTF-8 text that was decoded as Windows-1252 somewhere upstream ("CafÃ©" to "Café"),
+/// the value is left alone unless the whole of it decodes cleanly
+fn fix_encoding(value: &str) -> String {
+    let value = value.replace('\u{FEFF}', "");
+    if !value.contains(['Ã', 'Â', 'â']) {
+        return value;
+    }
+
+    let mut bytes = Vec::with_capacity(value.len());
+    for c in value.chars() {
+        match windows_1252_byte(c) {
+            Some(byte) => bytes.push(byte),
+            None => return value,
+        }
+    }
+
+    String::from_utf8(bytes).unwrap_or(value)
+}
+
+fn windows_1252_byte(c: char) -> Option<u8> {
+    let byte = match c {
+        '€' => 0x80,
+        '‚' => 0x82,
+        'ƒ' => 0x83,
+        '„' => 0x84,
+        '…' => 0x85,
+        '†' => 0x86,
+        '‡' => 0x87,
+        'ˆ' => 0x88,
+        '‰' => 0x89,
+        'Š' => 0x8A,
+        '‹' => 0x8B,
+        'Œ' => 0x8C,
+        'Ž' => 0x8E,
+        '‘' => 0x91,
+        '’' => 0x92,
+        '“' => 0x93,
+        '”' => 0x94,
+        '•' => 0x95,
+        '–' => 0x96,
+        '—' => 0x97,
+        '˜' => 0x98,
+        '™' => 0x99,
+        'š' => 0x9A,
+        '›' => 0x9B,
+        'œ' => 0x9C,
+        'ž' => 0x9E,
+        'Ÿ' => 0x9F,
+        c if (c as u32) < 0x100 => c as u8,
+        _ => return None,
+    };
+
+    Some(byte)
+}
+
+#[derive(Default)]
+struct Renderer {
+    html: String,
+    text: String,
+    markdown: String,
+    /// Item counter of each open list, `None` for bullet lists
+    lists: Vec<Option<usize>>,
+}
+
+impl Renderer {
+    fn children(&mut self, node: &Node) {
+        for child in node.get_child_nodes() {
+            self.node(&child);
+        }
+    }
+
+    fn node(&mut self, node: &Node) {
+        match node.get_type() {
+            Some(NodeType::TextNode) | Some(NodeType::CDataSectionNode) => {
+                self.inline_text(&node.get_content())
+            }
+            Some(NodeType::ElementNode) => self.element(node),
+            _ => {}
+        }
+    }
+
+    fn inline_text(&mut self, content: &str) {
+        // Fixed per text node too, one clean accent elsewhere keeps the whole value as is
+        let content = fix_encoding(content);
+        let collapsed = content
+            .replace('\u{A0}', " ")
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ");
+        let leading = content.starts_with(char::is_whitespace);
+        let trailing = content.ends_with(char::is_whitespace);
+        if collapsed.is_empty() {
+            if leading {
+                push_space(&mut self.html);
+                push_space(&mut self.text);
+                push_space(&mut self.markdown);
+            }
+            return;
+        }
+
+        for (buffer, value) in [
+            (&mut self.html, escape_html(&collapsed)),
+            (&mut self.text, collapsed.clone()),
+            (&mut self.markdown, escape_markdown(&collapsed)),
+        ] {
+            if leading {
+                push_space(buffer);
+            }
+            buffer.push_str(&value);
+            if trailing {
+                buffer.push(' ');
+            }
+        }
+    }
+
+    fn element(&mut self, node: &Node) {
+        let name = node.get_name().to_ascii_lowercase();
+        if DROPPED.contains(&name.as_str()) || is_hidden(node) {
+            return;
+        }
+
+        // A list nested in an item continues that item instead of starting a paragraph
+        let nested_list = (name == "ul" || name == "ol") && !self.lists.is_empty();
+        let block = BLOCKS.contains(&name.as_str()) && !nested_list;
+        if block {
+            self.paragraph();
+        }
+
+        let html_tag = match name.as_str() {
+            "a" => node
+                .get_attribute("href")
+                .filter(|href| is_safe_link(href))
+                .map(|href| format!("<a href=\"{}\">", escape_html(href.trim()))),
+            _ if ALLOWED.contains(&name.as_str()) => Some(format!("<{}>", name)),
+            _ => None,
+        };
+        if let Some(tag) = &html_tag {
+            self.html.push_str(tag);
+        }
+
+        match name.as_str() {
+            // Void element, no content and no closing tag
+            "br" => {
+                self.text.push('\n');
+                self.markdown.push_str("  \n");
+                return;
+            }
+            "ul" => self.lists.push(None),
+            "ol" => self.lists.push(Some(0)),
+            "li" => self.list_item(),
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = name[1..].parse().unwrap_or(1);
+                self.markdown.push_str(&"#".repeat(level));
+                self.markdown.push(' ');
+            }
+            _ => {}
+        }
+
+        let markdown_start = self.markdown.len();
+        self.children(node);
+
+        match name.as_str() {
+            "strong" | "b" => self.wrap_markdown(markdown_start, "**", "**"),
+            "em" | "i" => self.wrap_markdown(markdown_start, "*", "*"),
+            "a" => {
+                if let Some(href) = node.get_attribute("href").filter(|href| is_safe_link(href)) {
+                    let suffix = format!("]({})", href.trim().replace(')', "%29"));
+                    self.wrap_markdown(markdown_start, "[", &suffix);
+                }
+            }
+            "ul" | "ol" => {
+                self.lists.pop();
+            }
+            _ => {}
+        }
+
+        if html_tag.is_some() {
+            self.html.push_str(&format!("</{}>", name));
+        }
+        if block {
+            self.paragraph();
+        }
+    }
+
+    fn list_item(&mut self) {
+        let depth = self.lists.len().max(1) - 1;
+        let marker = match self.lists.last_mut() {
+            Some(Some(counter)) => {
+                *counter += 1;
+                format!("{}. ", counter)
+            }
+            _ => "- ".to_string(),
+        };
+
+        for buffer in [&mut self.text, &mut self.markdown] {
+            trim_trailing_spaces(buffer);
+            if !buffer.is_empty() && !buffer.ends_with('\n') {
+                buffer.push('\n');
+            }
+            buffer.push_str(&"  ".repeat(depth));
+            buffer.push_str(&marker);
+        }
+    }
+
+    /// Blank line between blocks, collapsed later by `tidy`
+    fn paragraph(&mut self) {
+        self.text.push_str("\n\n");
+        self.markdown.push_str("\n\n");
+    }
+
+    /// Surround what was rendered since `start` with Markdown markers, keeping the
+    /// whitespace outside of them so `** bold**` never happens
+    fn wrap_markdown(&mut self, start: usize, prefix: &str, suffix: &str) {
+        let inner = self.markdown.split_off(start);
+        let trimmed = inner.trim();
+        if trimmed.is_empty() {
+            self.markdown.push_str(&inner);
+            return;
+        }
+
+        if inner.starts_with(char::is_whitespace) {
+            push_space(&mut self.markdown);
+        }
+        self.markdown.push_str(prefix);
+        self.markdown.push_str(trimmed);
+        self.markdown.push_str(suffix);
+        if inner.ends_with(char::is_whitespace) {
+            self.markdown.push(' ');
+        }
+    }
+}
+
+fn is_hidden(node: &Node) -> bool {
+    let class = node.get_attribute("class").unwrap_or_default();
+    let style = node
+        .get_attribute("style")
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+        .replace(' ', "");
+
+    class
+        .split_whitespace()
+        .any(|class| class == "hide-me" || class == "hidden")
+        || style.contains("display:none")
+        || node.get_attribute("hidden").is_some()
+}
+
+fn is_safe_link(href: &str) -> bool {
+    let href = href.trim().to_ascii_lowercase();
+    href.starts_with("https://") || href.starts_with("http://") || href.starts_with("mailto:")
+}
+
+fn push_space(buffer: &mut String) {
+    if !buffer.is_empty() && !buffer.ends_with([' ', '\n']) {
+        buffer.push(' ');
+    }
+}
+
+fn trim_trailing_spaces(buffer: &mut String) {
+    let trimmed = buffer.trim_end_matches(' ').len();
+    buffer.truncate(trimmed);
+}
+
+/// Trim trailing spaces and keep at most one blank line between paragraphs
+/// Leading spaces are list indentation, the renderers never start a line with a stray space
+fn tidy(rendered: &str) -> String {
+    let mut output = String::new();
+    let mut blank = false;
+    for line in rendered.lines() {
+        if line.trim().is_empty() {
+            blank = !output.is_empty();
+            continue;
+        }
+        if blank {
+            output.push('\n');
+            blank = false;
+        }
+
+        // Markdown hard breaks are two trailing spaces, keep them
+        if line.ends_with("  ") {
+            output.push_str(line.trim_end());
+            output.push_str("  ");
+        } else {
+            output.push_str(line.trim_end());
+        }
+        output.push('\n');
+    }
+
+    output.trim_end().to_string()
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn escape_markdown(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_scripts_and_hidden_blocks_with_their_content() {
+        let rendition = sanitize(
+            "<p>Cuisinier</p><script>alert('x')</script><style>p{}</style>\
+             <div class=\"hide-me\">caché</div><p style=\"display: none\">masqué</p>\
+             <span hidden>invisible</span><iframe src=\"https://x.example\">cadre</iframe>",
+        );
+        assert_eq!(rendition.html, "<p>Cuisinier</p>");
+        assert_eq!(rendition.text, "Cuisinier");
+        assert_eq!(rendition.markdown, "Cuisinier");
+    }
+
+    #[test]
+    fn strips_attributes_and_unknown_tags() {
+        let rendition = sanitize(
+            "<p onclick=\"steal()\" class=\"x\">Temps <font color=\"red\">plein</font></p>",
+        );
+        assert_eq!(rendition.html, "<p>Temps plein</p>");
+    }
+
+    #[test]
+    fn keeps_only_safe_link_targets() {
+        let rendition = sanitize(
+            "<a href=\"javascript:alert(1)\">piège</a> \
+             <a href=\" JavaScript:alert(1)\">piège</a> \
+             <a href=\"data:text/html,x\">données</a> \
+             <a href=\"https://emplois.example/1\" onmouseover=\"x()\">offre</a>",
+        );
+        assert_eq!(
+            rendition.html,
+            "piège piège données <a href=\"https://emplois.example/1\">offre</a>"
+        );
+        assert_eq!(
+            rendition.markdown,
+            "piège piège données [offre](https://emplois.example/1)"
+        );
+        assert!(!rendition.html.to_ascii_lowercase().contains("javascript"));
+    }
+
+    #[test]
+    fn is_safe_link_accepts_web_and_mail_only() {
+        assert!(is_safe_link("https://a.example"));
+        assert!(is_safe_link(" HTTP://a.example"));
+        assert!(is_safe_link("mailto:rh@a.example"));
+        assert!(!is_safe_link("javascript:alert(1)"));
+        assert!(!is_safe_link("vbscript:x"));
+        assert!(!is_safe_link("//a.example"));
+    }
+
+    #[test]
+    fn decodes_double_escaped_html() {
+        // What the XML parser hands over for `&amp;lt;p&amp;gt;` in the feed
+        let rendition = sanitize("&lt;p&gt;Serveur &amp;amp; barman&lt;/p&gt;");
+        assert_eq!(rendition.html, "<p>Serveur &amp; barman</p>");
+        assert_eq!(rendition.text, "Serveur & barman");
+    }
+
+    #[test]
+    fn escaped_script_stays_inert() {
+        // Real markup next to it, the escaped tag is text and must stay escaped
+        let rendition = sanitize("&lt;script&gt;alert(1)&lt;/script&gt;<p>Texte</p>");
+        assert_eq!(
+            rendition.html,
+            "&lt;script&gt;alert(1)&lt;/script&gt;<p>Texte</p>"
+        );
+    }
+
+    #[test]
+    fn renders_lists_for_text_and_markdown() {
+        let rendition = sanitize("<ul><li>Soir</li><li><b>Fin</b> de semaine</li></ul>");
+        assert_eq!(rendition.text, "- Soir\n- Fin de semaine");
+        assert_eq!(rendition.markdown, "- Soir\n- **Fin** de semaine");
+    }
+
+    #[test]
+    fn fix_encoding_repairs_mojibake_only() {
+        assert_eq!(fix_encoding("CafÃ© prÃ©posÃ©"), "Café préposé");
+        assert_eq!(fix_encoding("Café"), "Café");
+        // One clean accent means the value was not mis-decoded as a whole
+        assert_eq!(fix_encoding("Ã© et é"), "Ã© et é");
+        assert_eq!(fix_encoding("\u{FEFF}Texte"), "Texte");
+    }
+}
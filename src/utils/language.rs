@@ -0,0 +1,156 @@
+use serde::Serialize;
+
+/// Language given to a translation when the feed says nothing and detection is unsure,
+/// most of our postings are in Québec
+pub const DEFAULT_LANGUAGE: &str = "fr";
+
+/// Below this confidence a detected language is not trusted and the default is used
+const MIN_CONFIDENCE: f32 = 0.6;
+
+/// Where the language of a translation comes from
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum LanguageSource {
+    /// Declared by the feed (`lang` attribute, `_FR`/`_EN` field)
+    Attribute,
+    /// Suffix of an identifier such as `jobCode` (`1001-fr`, `G77FR`)
+    Code,
+    /// Identified from the title and description
+    Detected,
+    /// Nothing reliable, `DEFAULT_LANGUAGE` was used
+    #[default]
+    Default,
+}
+
+/// Language from the suffix of an identifier (`1001-fr`, `TID-1001-EN`, `G77FR`), the
+/// suffix must not be the end of a word so `CHEF` or `GREEN` are not mistaken for one
+pub fn from_code(code: &str) -> Option<&'static str> {
+    let code = code.trim().to_ascii_lowercase();
+    for language in ["fr", "en"] {
+        if let Some(rest) = code.strip_suffix(language)
+            && !rest.ends_with(|c: char| c.is_ascii_alphabetic())
+        {
+            return Some(language);
+        }
+    }
+
+    None
+}
+
+const FRENCH_WORDS: &[&str] = &[
+    "le", "la", "les", "des", "du", "de", "un", "une", "et", "est", "sont", "pour", "avec", "dans",
+    "sur", "au", "aux", "nous", "vous", "notre", "nos", "votre", "vos", "ce", "cette", "ces",
+    "qui", "que", "en", "par", "pas", "plus", "être", "avoir", "son", "sa", "ses", "il", "elle",
+    "ils", "leur", "à", "où", "emploi", "poste", "travail", "équipe", "temps",
+];
+
+const ENGLISH_WORDS: &[&str] = &[
+    "the", "and", "of", "to", "in", "for", "with", "on", "at", "is", "are", "we", "you", "our",
+    "your", "this", "that", "these", "who", "which", "be", "have", "as", "an", "by", "from",
+    "will", "job", "work", "team", "time", "position", "join", "looking",
+];
+
+/// Identify French or English from common words and accents
+/// Returns the language and a confidence between 0 and 1 that grows with the share of
+/// evidence for it and with the amount of evidence
+pub fn detect(text: &str) -> (&'static str, f32) {
+    let mut french = 0u32;
+    let mut english = 0u32;
+
+    for word in text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_empty())
+    {
+        let word = word.to_lowercase();
+        // Accented words count as French even when they are not in the list
+        if FRENCH_WORDS.contains(&word.as_str())
+            || word.contains(['é', 'è', 'ê', 'à', 'ç', 'ù', 'û', 'ô', 'î'])
+        {
+            french += 1;
+        }
+        if ENGLISH_WORDS.contains(&word.as_str()) {
+            english += 1;
+        }
+    }
+
+    let total = french + english;
+    if total == 0 {
+        return (DEFAULT_LANGUAGE, 0.0);
+    }
+
+    let (language, hits) = if french >= english {
+        ("fr", french)
+    } else {
+        ("en", english)
+    };
+    // A couple of words is not much to go on, full confidence needs five
+    let evidence = (total as f32 / 5.0).min(1.0);
+
+    (language, hits as f32 / total as f32 * evidence)
+}
+
+/// Language for a translation the feed did not label
+pub fn identify(text: &str) -> (&'static str, LanguageSource, f32) {
+    let (language, confidence) = detect(text);
+    if confidence >= MIN_CONFIDENCE {
+        (language, LanguageSource::Detected, confidence)
+    } else {
+        (DEFAULT_LANGUAGE, LanguageSource::Default, confidence)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_ending_with_a_language() {
+        assert_eq!(from_code("G77FR"), Some("fr"));
+        assert_eq!(from_code("TID-1001-EN"), Some("en"));
+        assert_eq!(from_code(" 1001-fr "), Some("fr"));
+    }
+
+    #[test]
+    fn words_ending_like_a_language_are_not_codes() {
+        assert_eq!(from_code("CHEF"), None);
+        assert_eq!(from_code("GREEN"), None);
+        assert_eq!(from_code("G-77"), None);
+        assert_eq!(from_code(""), None);
+    }
+
+    #[test]
+    fn clear_text_is_identified() {
+        let (language, source, _) =
+            identify("Nous cherchons un cuisinier pour notre équipe dans le restaurant");
+        assert_eq!((language, source), ("fr", LanguageSource::Detected));
+
+        let (language, source, _) = identify("We are looking for a cook to join our team");
+        assert_eq!((language, source), ("en", LanguageSource::Detected));
+    }
+
+    #[test]
+    fn short_or_mixed_text_falls_back_to_the_default() {
+        // No known word at all
+        assert_eq!(
+            identify("Sous-chef"),
+            (DEFAULT_LANGUAGE, LanguageSource::Default, 0.0)
+        );
+
+        // Two French words are not enough evidence
+        let (language, confidence) = detect("Cuisinier de la brigade");
+        assert_eq!(language, "fr");
+        assert!(confidence < MIN_CONFIDENCE);
+        assert_eq!(
+            identify("Cuisinier de la brigade").1,
+            LanguageSource::Default
+        );
+
+        // Enough words, but nearly as many in each language
+        let (_, confidence) = detect("the team and la équipe et le");
+        assert!(confidence < MIN_CONFIDENCE);
+        assert_eq!(
+            identify("the team and la équipe et le").1,
+            LanguageSource::Default
+        );
+    }
+}
@@ -0,0 +1,242 @@
+use libxml::{
+    bindings,
+    tree::{Document, Node, NodeType},
+};
+
+use crate::utils::common::{ParseError, RejectedJob, XMLError};
+
+/// libxml2 stores element lines in 16 bits, records starting past this line cannot be told
+/// apart
+const MAX_LINE: i32 = 65535;
+
+/// A job record and the lines it spans, `end` is where the next element starts
+struct Record {
+    node: Node,
+    start: i32,
+    end: Option<i32>,
+    /// Something before the record may end on its first line
+    shared_start: bool,
+}
+
+/// Remove from `document` the job records that schema errors point to
+///
+/// Records are the elements `records_xpath` matches from the root element, an error belongs
+/// to the record whose lines contain it. When any error cannot be traced to exactly one
+/// record (outside of every record, on a line shared with another element, feed longer than
+/// `MAX_LINE`), `error` is returned so the import fails as it would without lenient mode.
+pub fn reject_invalid_records(
+    document: &Document,
+    mut error: ParseError,
+    records_xpath: &str,
+    id_xpath: &str,
+) -> Result<Vec<RejectedJob>, ParseError> {
+    let Some(records) = document
+        .get_root_element()
+        .and_then(|root| root.findnodes(records_xpath).ok())
+    else {
+        return Err(error);
+    };
+    let records: Vec<Record> = records
+        .into_iter()
+        .map(|node| {
+            let start = line_number(&node);
+            Record {
+                start,
+                end: following_line(&node),
+                shared_start: shares_start_line(&node, start),
+                node,
+            }
+        })
+        .collect();
+
+    let mut errors_by_record: Vec<Vec<usize>> = vec![vec![]; records.len()];
+    for (index, xml_error) in error.xml_errors.iter().enumerate() {
+        match record_of(&records, xml_error.line) {
+            Some(record) => errors_by_record[record].push(index),
+            None => {
+                error.message = format!(
+                    "{}, the error on line {} could not be traced to a single job",
+                    error.message, xml_error.line
+                );
+                return Err(error);
+            }
+        }
+    }
+
+    let mut xml_errors: Vec<Option<XMLError>> = error.xml_errors.into_iter().map(Some).collect();
+    let mut rejected = Vec::new();
+    for (mut record, indexes) in records.into_iter().zip(errors_by_record) {
+        if indexes.is_empty() {
+            continue;
+        }
+
+        rejected.push(RejectedJob {
+            job_id: record
+                .node
+                .findvalues(id_xpath)
+                .ok()
+                .and_then(|values| values.into_iter().next())
+                .map(|id| id.trim().to_string())
+                .unwrap_or_default(),
+            line: record.start,
+            errors: indexes
+                .into_iter()
+                .filter_map(|index| xml_errors[index].take())
+                .collect(),
+        });
+        record.node.unlink_node();
+    }
+
+    Ok(rejected)
+}
+
+fn line_number(node: &Node) -> i32 {
+    // Reads the line stored on the node by the parser, the node is alive for the call
+    let line = unsafe { bindings::xmlGetLineNo(node.node_ptr()) };
+    i32::try_from(line).unwrap_or(MAX_LINE)
+}
+
+/// Line of the first element after `node` and its content, `None` at the end of the document
+fn following_line(node: &Node) -> Option<i32> {
+    let mut current = node.clone();
+    loop {
+        let mut sibling = current.get_next_sibling();
+        while let Some(next) = sibling {
+            if next.get_type() == Some(NodeType::ElementNode) {
+                return Some(line_number(&next));
+            }
+            sibling = next.get_next_sibling();
+        }
+        current = current.get_parent()?;
+    }
+}
+
+/// Whether what precedes `node` may end on the line `node` starts on
+fn shares_start_line(node: &Node, start: i32) -> bool {
+    match node.get_prev_sibling() {
+        // Whitespace between two elements starts on the line the previous one ends on
+        Some(previous) if previous.get_type() == Some(NodeType::TextNode) => {
+            line_number(&previous) >= start
+        }
+        Some(_) => true,
+        None => node
+            .get_parent()
+            .is_none_or(|parent| line_number(&parent) >= start),
+    }
+}
+
+/// Index of the record an error line falls in, `None` if it is not exactly one
+fn record_of(records: &[Record], line: i32) -> Option<usize> {
+    let index = records
+        .iter()
+        .rposition(|record| record.start <= line && record.start > 0)?;
+    let record = &records[index];
+    if record.start >= MAX_LINE || (record.shared_start && line == record.start) {
+        return None;
+    }
+
+    // On the line where the next element starts, the error may belong to either
+    match record.end {
+        Some(end) if line >= end => None,
+        _ => Some(index),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libxml::parser::Parser;
+
+    use super::*;
+    use crate::utils::common::ParseErrorKind;
+
+    const FEED: &str = "<source>
+  <publisher>Tidan</publisher>
+  <job>
+    <jobCode>T-1</jobCode>
+    <title>Serveur</title>
+  </job>
+  <job>
+    <jobCode>T-2</jobCode>
+    <bogus/>
+  </job>
+  <footer/>
+</source>";
+
+    fn schema_error(lines: &[i32]) -> ParseError {
+        let mut error = ParseError::new(ParseErrorKind::SchemaInvalid, "Schema validation failed");
+        error.xml_errors = lines
+            .iter()
+            .map(|line| XMLError {
+                line: *line,
+                column: 0,
+                message: "This element is not expected".to_string(),
+                level: "error".to_string(),
+                domain: "schemas".to_string(),
+                code: 1871,
+            })
+            .collect();
+        error
+    }
+
+    fn reject(feed: &str, lines: &[i32]) -> (Document, Result<Vec<RejectedJob>, ParseError>) {
+        let document = Parser::default().parse_string(feed).unwrap();
+        let result = reject_invalid_records(&document, schema_error(lines), "job", "jobCode");
+        (document, result)
+    }
+
+    fn remaining_jobs(document: &Document) -> usize {
+        document
+            .get_root_element()
+            .unwrap()
+            .findnodes("job")
+            .unwrap()
+            .len()
+    }
+
+    #[test]
+    fn rejects_the_job_holding_the_error() {
+        let (document, result) = reject(FEED, &[9]);
+        let rejected = result.unwrap();
+
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].job_id, "T-2");
+        assert_eq!(rejected[0].line, 7);
+        assert_eq!(rejected[0].errors.len(), 1);
+        assert_eq!(remaining_jobs(&document), 1);
+    }
+
+    #[test]
+    fn errors_outside_jobs_fail_the_import() {
+        for line in [1, 2, 11] {
+            let (document, result) = reject(FEED, &[line]);
+            let error = result.unwrap_err();
+            assert!(error.message.contains(&format!("on line {}", line)));
+            assert_eq!(remaining_jobs(&document), 2);
+        }
+    }
+
+    #[test]
+    fn header_elements_are_not_records() {
+        let feed = FEED.replace("<publisher>", "<bogus/><publisher>");
+        let (_, result) = reject(&feed, &[2]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn single_line_feeds_cannot_be_traced() {
+        let feed = FEED.replace('\n', "");
+        let (document, result) = reject(&feed, &[1]);
+        assert!(result.is_err());
+        assert_eq!(remaining_jobs(&document), 2);
+    }
+
+    #[test]
+    fn job_starting_where_an_element_ends_is_ambiguous() {
+        let feed = FEED.replace("</job>\n  <job>", "</job><job>");
+        // The second job now starts on line 6, where the first one ends
+        let (_, result) = reject(&feed, &[6]);
+        assert!(result.is_err());
+        let (_, result) = reject(&feed, &[8]);
+        assert_eq!(result.unwrap()[0].job_id, "T-2");
+    }
+}
@@ -0,0 +1,916 @@
+use std::{borrow::Cow, collections::HashMap, path::Path};
+
+use libxml::tree::{Document, Node};
+use serde::Deserialize;
+
+use crate::utils::{
+    common::{
+        Company, FieldReport, Job, LabelMatch, LabelReport, Location, Translation, UnmappedLabel,
+    },
+    detect::has_nodes,
+    fields,
+    language::{self, LanguageSource},
+    registry::FeedParser,
+    text,
+};
+
+/// A feed format described by a TOML file instead of Rust code, see `mappings/formats` for
+/// examples
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct FormatMapping {
+    /// Format key clients send in the `format` multipart field
+    pub format: String,
+    /// XSD file in the schema directory
+    pub xsd: String,
+    /// XPath of the job records, relative to the root element
+    #[serde(default = "default_jobs")]
+    pub jobs: String,
+    /// Attribute of the record children holding the field key (`<Field name="Ville">`),
+    /// children are keyed by element name without it
+    /// Such keys are labels written by hand, looked up ignoring accents, case, quote style and
+    /// spacing
+    pub key_attribute: Option<String>,
+    #[serde(default)]
+    pub detect: DetectRule,
+    pub fields: Fields,
+    pub translations: Vec<TranslationMapping>,
+    /// Client quirks, keyed by profile name, applied on top of the base mapping
+    #[serde(default)]
+    pub profiles: HashMap<String, Profile>,
+}
+
+/// Overrides of one client, selected with the `profile` multipart field
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    /// Fields set here replace the base ones, the others are kept
+    pub fields: Fields,
+    /// Replace every base translation when not empty
+    pub translations: Vec<TranslationMapping>,
+}
+
+fn default_jobs() -> String {
+    "job".to_string()
+}
+
+/// How format detection recognizes the feed
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct DetectRule {
+    /// Name of the root element, any root when omitted
+    pub root: Option<String>,
+    /// XPaths, relative to the root, that must all match
+    pub requires: Vec<String>,
+    /// XPaths, relative to the root, of which one must match for the full score
+    pub any: Vec<String>,
+    /// Sniff score from 0 to 100 when the rule matches
+    pub score: u32,
+    /// Sniff score when `requires` matches but none of `any` does
+    pub partial_score: u32,
+}
+
+impl Default for DetectRule {
+    fn default() -> Self {
+        Self {
+            root: None,
+            requires: vec![],
+            any: vec![],
+            score: 80,
+            partial_score: 0,
+        }
+    }
+}
+
+/// Where each `Job` field comes from, fields left out stay empty
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Fields {
+    pub id: Option<Source>,
+    pub schedule: Option<Source>,
+    pub category: Option<Source>,
+    /// Comma-separated cities give one location each
+    pub city: Option<Source>,
+    pub province: Option<Source>,
+    pub postal_code: Option<Source>,
+    pub street_address: Option<Source>,
+    pub country: Option<Source>,
+    pub application_method: Option<Source>,
+    pub application_destination: Option<Source>,
+    pub company_id: Option<Source>,
+    pub company_name: Option<Source>,
+    pub company_city: Option<Source>,
+    pub company_postal_code: Option<Source>,
+    pub company_logo_url: Option<Source>,
+}
+
+impl Fields {
+    /// Job field and source of every field set
+    fn entries(&self) -> Vec<(&'static str, &Source)> {
+        [
+            ("id", &self.id),
+            ("schedule", &self.schedule),
+            ("category", &self.category),
+            ("city", &self.city),
+            ("province", &self.province),
+            ("postal_code", &self.postal_code),
+            ("street_address", &self.street_address),
+            ("country", &self.country),
+            ("application_method", &self.application_method),
+            ("application_destination", &self.application_destination),
+            ("company.id", &self.company_id),
+            ("company.name", &self.company_name),
+            ("company.city", &self.company_city),
+            ("company.postal_code", &self.company_postal_code),
+            ("company.logo_url", &self.company_logo_url),
+        ]
+        .into_iter()
+        .filter_map(|(field, source)| source.as_ref().map(|source| (field, source)))
+        .collect()
+    }
+
+    /// `self` with the fields `overrides` sets replaced
+    fn overlay(&self, overrides: Fields) -> Fields {
+        Fields {
+            id: overrides.id.or_else(|| self.id.clone()),
+            schedule: overrides.schedule.or_else(|| self.schedule.clone()),
+            category: overrides.category.or_else(|| self.category.clone()),
+            city: overrides.city.or_else(|| self.city.clone()),
+            province: overrides.province.or_else(|| self.province.clone()),
+            postal_code: overrides.postal_code.or_else(|| self.postal_code.clone()),
+            street_address: overrides
+                .street_address
+                .or_else(|| self.street_address.clone()),
+            country: overrides.country.or_else(|| self.country.clone()),
+            application_method: overrides
+                .application_method
+                .or_else(|| self.application_method.clone()),
+            application_destination: overrides
+                .application_destination
+                .or_else(|| self.application_destination.clone()),
+            company_id: overrides.company_id.or_else(|| self.company_id.clone()),
+            company_name: overrides.company_name.or_else(|| self.company_name.clone()),
+            company_city: overrides.company_city.or_else(|| self.company_city.clone()),
+            company_postal_code: overrides
+                .company_postal_code
+                .or_else(|| self.company_postal_code.clone()),
+            company_logo_url: overrides
+                .company_logo_url
+                .or_else(|| self.company_logo_url.clone()),
+        }
+    }
+}
+
+/// One `Translation` per record, records without a title nor a description are left out
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TranslationMapping {
+    /// Detected by the import pipeline when omitted
+    pub language: Option<LanguageRule>,
+    pub title: Option<Source>,
+    pub description: Option<Source>,
+    pub requirements: Option<Source>,
+}
+
+/// Value of a field
+///
+/// ```toml
+/// id = "referencenumber"                               # one key
+/// schedule = ["Type d'emploi", "Type d’emploi"]        # first key with a value
+/// application_method = { value = "url" }               # constant
+/// category = { join = ["category", "subcategory"] }    # every value, ", " separated
+/// ```
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum Source {
+    Key(String),
+    Fallbacks(Vec<String>),
+    Value(Constant),
+    Join(Join),
+}
+
+impl Source {
+    /// Record keys the value is read from
+    fn keys(&self) -> Vec<&str> {
+        match self {
+            Source::Key(key) => vec![key],
+            Source::Fallbacks(keys) => keys.iter().map(|key| key.as_str()).collect(),
+            Source::Value(_) => vec![],
+            Source::Join(join) => join.join.iter().map(|key| key.as_str()).collect(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Constant {
+    pub value: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Join {
+    pub join: Vec<String>,
+    #[serde(default = "default_separator")]
+    pub separator: String,
+}
+
+fn default_separator() -> String {
+    ", ".to_string()
+}
+
+/// Language of a translation
+///
+/// ```toml
+/// language = "fr"                                      # always French
+/// language = { code = ["jobCode", "businessProcessId"] } # suffix of the first code with one
+/// ```
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum LanguageRule {
+    Fixed(String),
+    Code { code: Vec<String> },
+}
+
+/// Values of a job record keyed by element name or `key_attribute`, in document order
+struct Record {
+    values: HashMap<String, Vec<String>>,
+    /// Keyed by `text::label_key` of the labels
+    by_label: bool,
+}
+
+impl Record {
+    fn read(record: &Node, key_attribute: Option<&str>) -> Self {
+        let mut values: HashMap<String, Vec<String>> = HashMap::new();
+        for child in record.get_child_elements() {
+            let key = match key_attribute {
+                Some(attribute) => match child.get_attribute(attribute) {
+                    Some(label) => text::label_key(&label),
+                    None => continue,
+                },
+                None => child.get_name(),
+            };
+            values.entry(key).or_default().push(child.get_content());
+        }
+
+        Self {
+            values,
+            by_label: key_attribute.is_some(),
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&Vec<String>> {
+        let key = if self.by_label {
+            Cow::Owned(text::label_key(key))
+        } else {
+            Cow::Borrowed(key)
+        };
+        self.values.get(key.as_ref())
+    }
+
+    fn first(&self, key: &str) -> Option<&str> {
+        self.get(key)?
+            .iter()
+            .map(|value| value.as_str())
+            .find(|value| !value.trim().is_empty())
+    }
+
+    fn value(&self, source: Option<&Source>) -> String {
+        let Some(source) = source else {
+            return String::new();
+        };
+
+        match source {
+            Source::Key(key) => self.first(key).unwrap_or_default().to_string(),
+            Source::Fallbacks(keys) => keys
+                .iter()
+                .find_map(|key| self.first(key))
+                .unwrap_or_default()
+                .to_string(),
+            Source::Value(constant) => constant.value.clone(),
+            Source::Join(join) => join
+                .join
+                .iter()
+                .filter_map(|key| self.get(key))
+                .flatten()
+                .map(|value| value.trim())
+                .filter(|value| !value.is_empty())
+                .collect::<Vec<&str>>()
+                .join(&join.separator),
+        }
+    }
+
+    fn language(&self, rule: Option<&LanguageRule>) -> (String, LanguageSource) {
+        match rule {
+            Some(LanguageRule::Fixed(language)) => (language.clone(), LanguageSource::Attribute),
+            Some(LanguageRule::Code { code }) => code
+                .iter()
+                .filter_map(|key| self.first(key))
+                .find_map(language::from_code)
+                .map_or((String::new(), LanguageSource::Default), |language| {
+                    (language.to_string(), LanguageSource::Code)
+                }),
+            None => (String::new(), LanguageSource::Default),
+        }
+    }
+}
+
+/// A record key the mapping reads
+struct MappedKey {
+    /// As written in the mapping
+    key: String,
+    /// `text::label_key` of the key
+    lookup: String,
+    /// Job fields it fills
+    fields: Vec<String>,
+}
+
+/// Keys read by a mapping, keys that only differ in the way labels are compared are one key
+fn mapped_keys(mapping: &FormatMapping) -> Vec<MappedKey> {
+    let mut uses: Vec<(&str, String)> = mapping
+        .fields
+        .entries()
+        .into_iter()
+        .flat_map(|(field, source)| {
+            source
+                .keys()
+                .into_iter()
+                .map(move |key| (key, field.to_string()))
+        })
+        .collect();
+    for translation in &mapping.translations {
+        for (field, source) in [
+            ("title", &translation.title),
+            ("description", &translation.description),
+            ("requirements", &translation.requirements),
+        ] {
+            for key in source.iter().flat_map(|source| source.keys()) {
+                uses.push((key, format!("translations.{}", field)));
+            }
+        }
+        if let Some(LanguageRule::Code { code }) = &translation.language {
+            for key in code {
+                uses.push((key, "translations.language".to_string()));
+            }
+        }
+    }
+
+    let mut keys: Vec<MappedKey> = Vec::new();
+    for (key, field) in uses {
+        let lookup = text::label_key(key);
+        match keys.iter_mut().find(|mapped| mapped.lookup == lookup) {
+            Some(mapped) => {
+                if !mapped.fields.contains(&field) {
+                    mapped.fields.push(field);
+                }
+            }
+            None => keys.push(MappedKey {
+                key: key.to_string(),
+                lookup,
+                fields: vec![field],
+            }),
+        }
+    }
+    keys
+}
+
+/// Generic parser driven by a `FormatMapping`
+pub struct MappedParser {
+    mapping: FormatMapping,
+    id_xpath: String,
+    keys: Vec<MappedKey>,
+    /// Same format with the overrides of a profile applied, sorted by name
+    profiles: Vec<(String, MappedParser)>,
+}
+
+impl MappedParser {
+    pub fn new(mut mapping: FormatMapping) -> Result<Self, String> {
+        if mapping.format.trim().is_empty() {
+            return Err("format is empty".to_string());
+        }
+        if mapping.fields.id.is_none() {
+            return Err("fields.id is required".to_string());
+        }
+        if mapping.translations.is_empty() {
+            return Err("at least one [[translations]] entry is required".to_string());
+        }
+        for translation in &mapping.translations {
+            if translation.title.is_none() {
+                return Err("every [[translations]] entry needs a title".to_string());
+            }
+            if let Some(LanguageRule::Fixed(language)) = &translation.language
+                && language.trim().is_empty()
+            {
+                return Err("translations.language is empty".to_string());
+            }
+        }
+
+        // Lenient imports name rejected records by the element holding the id
+        let id_key = match &mapping.fields.id {
+            Some(Source::Key(key)) => Some(key.as_str()),
+            Some(Source::Fallbacks(keys)) => keys.first().map(|key| key.as_str()),
+            _ => None,
+        };
+        let id_xpath = match (id_key, &mapping.key_attribute) {
+            (Some(key), Some(attribute)) => format!("*[@{}={}]", attribute, xpath_literal(key)),
+            (Some(key), None) => key.to_string(),
+            (None, _) => String::new(),
+        };
+
+        let mut profiles = Vec::new();
+        for (name, profile) in std::mem::take(&mut mapping.profiles) {
+            if name.trim().is_empty() || name.contains(':') {
+                return Err(format!("invalid profile name {:?}", name));
+            }
+            let translations = if profile.translations.is_empty() {
+                mapping.translations.clone()
+            } else {
+                profile.translations
+            };
+            let resolved = FormatMapping {
+                format: mapping.format.clone(),
+                xsd: mapping.xsd.clone(),
+                jobs: mapping.jobs.clone(),
+                key_attribute: mapping.key_attribute.clone(),
+                detect: DetectRule::default(),
+                fields: mapping.fields.overlay(profile.fields),
+                translations,
+                profiles: HashMap::new(),
+            };
+            let parser =
+                MappedParser::new(resolved).map_err(|e| format!("profile {}: {}", name, e))?;
+            profiles.push((name, parser));
+        }
+        profiles.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        Ok(Self {
+            keys: mapped_keys(&mapping),
+            mapping,
+            id_xpath,
+            profiles,
+        })
+    }
+
+    fn job(&self, record: &Record) -> Job {
+        let fields = &self.mapping.fields;
+        let city = record.value(fields.city.as_ref());
+        let province = record.value(fields.province.as_ref());
+
+        let translations = self
+            .mapping
+            .translations
+            .iter()
+            .map(|translation| {
+                let (language, language_source) = record.language(translation.language.as_ref());
+                Translation {
+                    language,
+                    language_source,
+                    title: record.value(translation.title.as_ref()),
+                    description: record.value(translation.description.as_ref()),
+                    requirements: record.value(translation.requirements.as_ref()),
+                    ..Default::default()
+                }
+            })
+            .filter(|translation| {
+                !translation.title.trim().is_empty() || !translation.description.trim().is_empty()
+            })
+            .collect();
+
+        Job {
+            id: record.value(fields.id.as_ref()),
+            schedule: record.value(fields.schedule.as_ref()),
+            category: record.value(fields.category.as_ref()),
+            locations: Location::split_cities(
+                &city,
+                Location {
+                    province: province.clone(),
+                    postal_code: record.value(fields.postal_code.as_ref()),
+                    street_address: record.value(fields.street_address.as_ref()),
+                    country: record.value(fields.country.as_ref()),
+                    ..Default::default()
+                },
+            ),
+            city,
+            province,
+            application_method: record.value(fields.application_method.as_ref()),
+            application_destination: record.value(fields.application_destination.as_ref()),
+            company: Company {
+                id: record.value(fields.company_id.as_ref()),
+                name: record.value(fields.company_name.as_ref()),
+                city: record.value(fields.company_city.as_ref()),
+                postal_code: record.value(fields.company_postal_code.as_ref()),
+                logo_url: record.value(fields.company_logo_url.as_ref()),
+            },
+            translations,
+            ..Default::default()
+        }
+    }
+}
+
+impl FeedParser for MappedParser {
+    fn name(&self) -> &str {
+        &self.mapping.format
+    }
+
+    fn xsd_file(&self) -> &str {
+        &self.mapping.xsd
+    }
+
+    fn parse_into_jobs(&self, document: &Document) -> Result<Vec<Job>, String> {
+        let root = document
+            .get_root_element()
+            .ok_or("Document has no root element")?;
+        let records = root
+            .findnodes(&self.mapping.jobs)
+            .map_err(|e| format!("Error finding job nodes: {:?}", e))?;
+
+        Ok(records
+            .iter()
+            .map(|record| self.job(&Record::read(record, self.mapping.key_attribute.as_deref())))
+            .collect())
+    }
+
+    fn records_xpath(&self) -> &str {
+        &self.mapping.jobs
+    }
+
+    fn id_xpath(&self) -> &str {
+        &self.id_xpath
+    }
+
+    fn field_names(&self) -> Vec<&str> {
+        if self.mapping.key_attribute.is_none() {
+            return vec![];
+        }
+
+        let mut names: Vec<&str> = self.keys.iter().map(|mapped| mapped.key.as_str()).collect();
+        for (_, profile) in &self.profiles {
+            names.extend(profile.field_names());
+        }
+        names
+    }
+
+    fn field_report(&self, document: &Document) -> FieldReport {
+        let mapped: Vec<&str> = self.keys.iter().map(|mapped| mapped.key.as_str()).collect();
+        fields::report(
+            self.name(),
+            document,
+            self.records_xpath(),
+            self.mapping.key_attribute.as_deref(),
+            &mapped,
+        )
+    }
+
+    fn match_labels(&self, document: &Document) -> LabelReport {
+        let mut report = LabelReport::default();
+        let Some(attribute) = &self.mapping.key_attribute else {
+            return report;
+        };
+        let Some(records) = document
+            .get_root_element()
+            .and_then(|root| root.findnodes(&self.mapping.jobs).ok())
+        else {
+            return report;
+        };
+
+        for record in records {
+            // Jobs are counted once per label
+            let mut labels: Vec<String> = record
+                .get_child_elements()
+                .iter()
+                .filter_map(|child| child.get_attribute(attribute))
+                .collect();
+            labels.sort();
+            labels.dedup();
+
+            for label in labels {
+                let lookup = text::label_key(&label);
+                let Some(mapped) = self.keys.iter().find(|mapped| mapped.lookup == lookup) else {
+                    UnmappedLabel::count(&mut report.unused, label);
+                    continue;
+                };
+                match report
+                    .matched
+                    .iter_mut()
+                    .find(|entry| entry.label == label && entry.key == mapped.key)
+                {
+                    Some(entry) => entry.jobs += 1,
+                    None => report.matched.push(LabelMatch {
+                        label,
+                        key: mapped.key.clone(),
+                        fields: mapped.fields.clone(),
+                        jobs: 1,
+                    }),
+                }
+            }
+        }
+
+        report
+    }
+
+    fn profiles(&self) -> Vec<&str> {
+        self.profiles
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    fn profile(&self, name: &str) -> Option<&dyn FeedParser> {
+        self.profiles
+            .iter()
+            .find(|(profile, _)| profile == name)
+            .map(|(_, parser)| parser as &dyn FeedParser)
+    }
+
+    fn sniff(&self, root: &Node) -> u32 {
+        let detect = &self.mapping.detect;
+        let root_matches = detect
+            .root
+            .as_ref()
+            .is_none_or(|name| root.get_name() == *name);
+        if !root_matches || !detect.requires.iter().all(|xpath| has_nodes(root, xpath)) {
+            return 0;
+        }
+        if detect.any.is_empty() || detect.any.iter().any(|xpath| has_nodes(root, xpath)) {
+            detect.score.min(100)
+        } else {
+            detect.partial_score.min(100)
+        }
+    }
+}
+
+/// Load every `*.toml` format mapping of a directory, in file name order
+pub fn load_dir(dir: &Path) -> Result<Vec<MappedParser>, String> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| format!("Could not read mapping directory {}: {}", dir.display(), e))?;
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "toml")
+        })
+        .collect();
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|path| {
+            let content = std::fs::read_to_string(path)
+                .map_err(|e| format!("Could not read mapping {}: {}", path.display(), e))?;
+            let mapping: FormatMapping = toml::from_str(&content)
+                .map_err(|e| format!("Invalid mapping {}: {}", path.display(), e))?;
+            MappedParser::new(mapping)
+                .map_err(|e| format!("Invalid mapping {}: {}", path.display(), e))
+        })
+        .collect()
+}
+
+/// Quote a string for an XPath expression, XPath 1.0 has no escape sequences
+fn xpath_literal(value: &str) -> String {
+    if !value.contains('\'') {
+        format!("'{}'", value)
+    } else if !value.contains('"') {
+        format!("\"{}\"", value)
+    } else {
+        let parts: Vec<String> = value
+            .split('\'')
+            .map(|part| format!("'{}'", part))
+            .collect();
+        format!("concat({})", parts.join(", \"'\", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libxml::parser::Parser;
+
+    use super::*;
+
+    fn parser(mapping: &str) -> Result<MappedParser, String> {
+        MappedParser::new(toml::from_str(mapping).map_err(|e| e.to_string())?)
+    }
+
+    fn document(xml: &str) -> Document {
+        Parser::default().parse_string(xml).unwrap()
+    }
+
+    fn jobs(parser: &dyn FeedParser, xml: &str) -> Vec<Job> {
+        parser.parse_into_jobs(&document(xml)).unwrap()
+    }
+
+    /// A small document of every shipped format, with the id and languages it maps to
+    const SAMPLES: &[(&str, &str, &str, &[&str])] = &[
+        (
+            "xml-grandio",
+            "<source><job><jobCode>G77FR</jobCode><businessProcessId>G-77</businessProcessId>\
+             <title>Cuisinier</title><description>Cuisiner</description>\
+             <Restaurant>Grandio</Restaurant></job></source>",
+            "G-77",
+            &["fr"],
+        ),
+        (
+            "xml-pscout",
+            "<records><record><field name=\"JobReferenceNumber\">PS-1</field>\
+             <field name=\"JobTitle_FR\">Cuisinier</field>\
+             <field name=\"JobTitle_EN\">Cook</field></record></records>",
+            "PS-1",
+            &["fr", "en"],
+        ),
+        (
+            "xml-tidan",
+            "<source><job><jobCode>TID-1-EN</jobCode><businessProcessId>1</businessProcessId>\
+             <title>Cook</title><description>Cooking</description></job></source>",
+            "TID-1-EN",
+            &["en"],
+        ),
+        (
+            "xml-zohoquintessence",
+            "<source><job><referencenumber>ZQ-9</referencenumber><title>Cuisinier</title>\
+             </job></source>",
+            "ZQ-9",
+            &[""],
+        ),
+        (
+            "xml-zohorecruit",
+            "<jobs><job><Field name=\"Reference Number\">ZR-1</Field>\
+             <Field name=\"Titre de la publication\">Préposé</Field></job></jobs>",
+            "ZR-1",
+            &[""],
+        ),
+    ];
+
+    #[test]
+    fn shipped_mappings_load_and_map() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("mappings/formats");
+        let parsers = load_dir(&dir).unwrap();
+        let names: Vec<&str> = parsers.iter().map(|parser| parser.name()).collect();
+        let sampled: Vec<&str> = SAMPLES.iter().map(|(format, ..)| *format).collect();
+        assert_eq!(names, sampled, "every shipped mapping needs a sample");
+
+        for (parser, (format, xml, id, languages)) in parsers.iter().zip(SAMPLES) {
+            let document = document(xml);
+            assert!(
+                parser.sniff(&document.get_root_element().unwrap()) > 0,
+                "{} does not recognize its sample",
+                format
+            );
+            let jobs = parser.parse_into_jobs(&document).unwrap();
+            assert_eq!(jobs.len(), 1, "{}", format);
+            assert_eq!(jobs[0].id, *id, "{}", format);
+            let mapped: Vec<&str> = jobs[0]
+                .translations
+                .iter()
+                .map(|translation| translation.language.as_str())
+                .collect();
+            assert_eq!(mapped, *languages, "{}", format);
+        }
+    }
+
+    #[test]
+    fn shipped_pscout_mapping_keeps_the_schedule_as_written() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("mappings/formats");
+        let parsers = load_dir(&dir).unwrap();
+        let pscout = parsers
+            .iter()
+            .find(|parser| parser.name() == "xml-pscout")
+            .unwrap();
+        let jobs = jobs(
+            pscout,
+            "<records><record><field name=\"JobReferenceNumber\">PS-1</field>\
+             <field name=\"JobTitle_FR\">Cuisinier</field>\
+             <field name=\"Schedule\">Full-time - Seasonal</field></record></records>",
+        );
+        assert_eq!(jobs[0].schedule, "Full-time - Seasonal");
+    }
+
+    const SOURCES: &str = r#"
+        format = "xml-test"
+        xsd = "xml-test.xsd"
+
+        [fields]
+        id = "id"
+        schedule = "schedule"
+        city = ["ville", "city"]
+        application_method = { value = "url" }
+        category = { join = ["a", "b", "c"], separator = " / " }
+
+        [[translations]]
+        language = "fr"
+        title = "title"
+    "#;
+
+    #[test]
+    fn sources_read_keys_fallbacks_constants_and_joins() {
+        let parser = parser(SOURCES).unwrap();
+        let jobs = jobs(
+            &parser,
+            "<jobs><job><id>1</id><ville> </ville><city>Laval</city>\
+             <a>Cuisine</a><b> </b><c>Hôtel</c><title>Cuisinier</title></job></jobs>",
+        );
+
+        assert_eq!(jobs[0].id, "1");
+        assert_eq!(jobs[0].schedule, "");
+        assert_eq!(jobs[0].city, "Laval");
+        assert_eq!(jobs[0].application_method, "url");
+        assert_eq!(jobs[0].category, "Cuisine / Hôtel");
+        assert_eq!(jobs[0].translations[0].language, "fr");
+    }
+
+    #[test]
+    fn records_without_title_nor_description_have_no_translation() {
+        let parser = parser(SOURCES).unwrap();
+        let jobs = jobs(
+            &parser,
+            "<jobs><job><id>1</id><title> </title></job></jobs>",
+        );
+        assert!(jobs[0].translations.is_empty());
+    }
+
+    #[test]
+    fn key_attribute_matches_labels_loosely() {
+        let parser = parser(
+            r#"
+            format = "xml-test"
+            xsd = "xml-test.xsd"
+            key_attribute = "name"
+
+            [fields]
+            id = "Reference Number"
+            schedule = "Type d'emploi"
+            province = "État/Province"
+
+            [[translations]]
+            title = "Titre"
+            "#,
+        )
+        .unwrap();
+        let jobs = jobs(
+            &parser,
+            "<jobs><job><Field name=\"reference  number\">ZR-1</Field>\
+             <Field name=\"Type d’emploi\">Temps plein</Field>\
+             <Field name=\"Etat/province\">QC</Field>\
+             <Field>Sans nom</Field><Field name=\"Titre\">Préposé</Field></job></jobs>",
+        );
+
+        assert_eq!(jobs[0].id, "ZR-1");
+        assert_eq!(jobs[0].schedule, "Temps plein");
+        assert_eq!(jobs[0].province, "QC");
+        assert_eq!(jobs[0].translations[0].title, "Préposé");
+        assert_eq!(parser.id_xpath(), "*[@name='Reference Number']");
+    }
+
+    #[test]
+    fn profiles_replace_the_fields_they_set() {
+        let parser = parser(&format!(
+            "{}\n[profiles.client.fields]\ncity = \"municipalite\"\n",
+            SOURCES
+        ))
+        .unwrap();
+        assert_eq!(parser.profiles(), ["client"]);
+
+        let xml = "<jobs><job><id>1</id><city>Laval</city><municipalite>Lévis</municipalite>\
+                   <a>Cuisine</a><title>Cuisinier</title></job></jobs>";
+        let profiled = jobs(parser.profile("client").unwrap(), xml);
+        assert_eq!(profiled[0].city, "Lévis");
+        assert_eq!(profiled[0].category, "Cuisine");
+        assert_eq!(jobs(&parser, xml)[0].city, "Laval");
+    }
+
+    #[test]
+    fn invalid_mappings_are_rejected() {
+        let header = "format = \"xml-test\"\nxsd = \"xml-test.xsd\"\n";
+        let error = |mapping: &str| parser(&format!("{}{}", header, mapping)).err().unwrap();
+
+        assert_eq!(
+            error("translations = []\n[fields]\nschedule = \"schedule\"\n"),
+            "fields.id is required"
+        );
+        assert_eq!(
+            error("translations = []\n[fields]\nid = \"id\"\n"),
+            "at least one [[translations]] entry is required"
+        );
+        assert_eq!(
+            error("[fields]\nid = \"id\"\n[[translations]]\ndescription = \"description\"\n"),
+            "every [[translations]] entry needs a title"
+        );
+    }
+
+    #[test]
+    fn xpath_literal_quotes_any_value() {
+        assert_eq!(xpath_literal("Ville"), "'Ville'");
+        assert_eq!(xpath_literal("Type d'emploi"), "\"Type d'emploi\"");
+        assert_eq!(
+            xpath_literal("l'\"hôtel\""),
+            "concat('l', \"'\", '\"hôtel\"')"
+        );
+
+        let document = document("<job><Field name=\"l'&quot;hôtel&quot;\">1</Field></job>");
+        let xpath = format!("Field[@name={}]", xpath_literal("l'\"hôtel\""));
+        let found = document
+            .get_root_element()
+            .unwrap()
+            .findnodes(&xpath)
+            .unwrap();
+        assert_eq!(found.len(), 1);
+    }
+}
@@ -0,0 +1,275 @@
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::utils::{
+    common::{Job, MergeConflict},
+    language,
+};
+
+/// How records of the same posting are recognized in feeds that send one record per language
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum MergeKey {
+    /// Ids that only differ by a language suffix (`G-77-fr`, `G-77-en`), the merged job
+    /// keeps the id without it
+    LanguageSuffix,
+    /// Records sharing the exact same id
+    Id,
+}
+
+impl FromStr for MergeKey {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "language-suffix" => Ok(Self::LanguageSuffix),
+            "id" => Ok(Self::Id),
+            _ => Err("expected language-suffix or id".to_string()),
+        }
+    }
+}
+
+impl MergeKey {
+    fn key(&self, id: &str) -> String {
+        let id = id.trim();
+        match self {
+            Self::LanguageSuffix if language::from_code(id).is_some() => id[..id.len() - 2]
+                .trim_end_matches(['-', '_', '.', ' '])
+                .to_string(),
+            _ => id.to_string(),
+        }
+    }
+}
+
+/// Fold the records sharing a key into the first one, in feed order
+/// A record is only folded into a job that does not have its languages yet, a second record
+/// in the same language is a separate job left for `duplicates::remove` to report
+/// Translations are appended, shared fields keep the first non-empty value and any other
+/// value is reported as a conflict unless `same(field, kept, other)` says both mean the same
+/// thing, as with a schedule written in each language
+pub fn merge(
+    jobs: Vec<Job>,
+    rule: MergeKey,
+    same: impl Fn(&str, &str, &str) -> bool,
+    conflicts: &mut Vec<MergeConflict>,
+) -> Vec<Job> {
+    let mut merged: Vec<Job> = Vec::with_capacity(jobs.len());
+
+    for mut job in jobs {
+        let key = rule.key(&job.id);
+        let new_languages = languages(&job);
+        let into = merged.iter_mut().find(|existing| {
+            let languages = languages(existing);
+            existing.id == key
+                && !new_languages.is_empty()
+                && new_languages
+                    .iter()
+                    .all(|language| !languages.contains(language))
+        });
+        match into {
+            Some(existing) => fold(existing, job, &same, conflicts),
+            None => {
+                job.id = key;
+                merged.push(job);
+            }
+        }
+    }
+
+    merged
+}
+
+/// Languages of the translations of a record, identified from the text when not labelled
+/// since the pipeline only does it after merging
+fn languages(job: &Job) -> Vec<&str> {
+    job.translations
+        .iter()
+        .map(|translation| {
+            if translation.language.is_empty() {
+                let sample = format!("{} {}", translation.title, translation.description);
+                language::identify(&sample).0
+            } else {
+                translation.language.as_str()
+            }
+        })
+        .collect()
+}
+
+fn fold(
+    into: &mut Job,
+    other: Job,
+    same: &impl Fn(&str, &str, &str) -> bool,
+    conflicts: &mut Vec<MergeConflict>,
+) {
+    let job_id = into.id.clone();
+    let mut keep = |field: &'static str, kept: &mut String, value: String| {
+        if value.trim().is_empty() || value.trim() == kept.trim() {
+            return;
+        }
+        if kept.trim().is_empty() {
+            *kept = value;
+            return;
+        }
+        if same(field, kept, &value) {
+            return;
+        }
+        conflicts.push(MergeConflict {
+            job_id: job_id.clone(),
+            field,
+            kept: kept.clone(),
+            ignored: value,
+        });
+    };
+
+    keep("schedule", &mut into.schedule, other.schedule);
+    keep("category", &mut into.category, other.category);
+    keep("city", &mut into.city, other.city);
+    keep("province", &mut into.province, other.province);
+    keep(
+        "application_method",
+        &mut into.application_method,
+        other.application_method,
+    );
+    keep(
+        "application_destination",
+        &mut into.application_destination,
+        other.application_destination,
+    );
+    keep("company.id", &mut into.company.id, other.company.id);
+    keep("company.name", &mut into.company.name, other.company.name);
+    keep("company.city", &mut into.company.city, other.company.city);
+    keep(
+        "company.postal_code",
+        &mut into.company.postal_code,
+        other.company.postal_code,
+    );
+    keep(
+        "company.logo_url",
+        &mut into.company.logo_url,
+        other.company.logo_url,
+    );
+
+    // Built from the city field, which is already compared
+    if into.locations.is_empty() {
+        into.locations = other.locations;
+    }
+
+    for translation in other.translations {
+        // Two records in the same language are not translations of each other
+        let duplicate = into.translations.iter().find(|existing| {
+            !translation.language.is_empty() && existing.language == translation.language
+        });
+        if let Some(existing) = duplicate {
+            conflicts.push(MergeConflict {
+                job_id: job_id.clone(),
+                field: "translations",
+                kept: existing.title.clone(),
+                ignored: translation.title,
+            });
+            continue;
+        }
+        into.translations.push(translation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{
+        common::Translation,
+        duplicates::{self, DuplicatePolicy},
+    };
+
+    fn record(id: &str, language: &str, title: &str) -> Job {
+        Job {
+            id: id.to_string(),
+            city: "Montréal".to_string(),
+            translations: vec![Translation {
+                language: language.to_string(),
+                title: title.to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn run(jobs: Vec<Job>, conflicts: &mut Vec<MergeConflict>) -> Vec<Job> {
+        merge(jobs, MergeKey::LanguageSuffix, |_, _, _| false, conflicts)
+    }
+
+    #[test]
+    fn key_strips_the_language_suffix() {
+        assert_eq!(MergeKey::LanguageSuffix.key("G-77-fr"), "G-77");
+        assert_eq!(MergeKey::LanguageSuffix.key("G77FR"), "G77");
+        assert_eq!(MergeKey::LanguageSuffix.key("G-77"), "G-77");
+        assert_eq!(MergeKey::Id.key(" G-77-fr "), "G-77-fr");
+    }
+
+    #[test]
+    fn folds_translations_of_one_posting() {
+        let mut conflicts = vec![];
+        let jobs = run(
+            vec![
+                record("G-77-fr", "fr", "Serveur"),
+                record("G-77-en", "en", "Waiter"),
+            ],
+            &mut conflicts,
+        );
+
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].id, "G-77");
+        assert_eq!(jobs[0].translations.len(), 2);
+        assert!(conflicts.is_empty());
+    }
+
+    #[test]
+    fn keeps_same_language_records_apart() {
+        let mut conflicts = vec![];
+        let jobs = run(
+            vec![
+                record("G-77", "fr", "Serveur"),
+                record("G-77", "fr", "Cuisinier"),
+            ],
+            &mut conflicts,
+        );
+
+        assert_eq!(jobs.len(), 2);
+        assert!(jobs.iter().all(|job| job.id == "G-77"));
+        assert!(conflicts.is_empty());
+    }
+
+    #[test]
+    fn same_language_records_reach_the_duplicate_policy() {
+        let mut conflicts = vec![];
+        let jobs = run(
+            vec![
+                record("G-77-fr", "fr", "Serveur"),
+                record("G-77-en", "en", "Waiter"),
+                record("G-77-fr", "fr", "Cuisinier"),
+            ],
+            &mut conflicts,
+        );
+        assert_eq!(jobs.len(), 2);
+
+        let mut report = vec![];
+        let kept = duplicates::remove(jobs, DuplicatePolicy::Reject, false, &mut report);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].job_id, "G-77");
+        assert_eq!(report[0].title, "Cuisinier");
+    }
+
+    #[test]
+    fn identifies_unlabelled_languages_before_folding() {
+        let mut conflicts = vec![];
+        let jobs = run(
+            vec![
+                record("T-1-fr", "", "Préposé à l'entretien pour notre équipe"),
+                record("T-1-en", "", "Maintenance attendant for the team"),
+            ],
+            &mut conflicts,
+        );
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].translations.len(), 2);
+    }
+}
@@ -0,0 +1,117 @@
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder,
+    exponential_buckets,
+};
+
+/// Label used when the import did not resolve to a registered format, so arbitrary client
+/// input never becomes a label value
+pub const UNKNOWN_FORMAT: &str = "unknown";
+
+/// Prometheus collectors for `/import`, rendered by `/metrics`
+pub struct Metrics {
+    registry: Registry,
+    imports: IntCounterVec,
+    duration: HistogramVec,
+    upload_bytes: HistogramVec,
+    jobs: IntGaugeVec,
+    jobs_total: IntCounterVec,
+}
+
+impl Metrics {
+    pub fn new() -> Result<Self, String> {
+        let imports = IntCounterVec::new(
+            Opts::new("hj2_imports_total", "Imports by format and outcome"),
+            &["format", "outcome"],
+        )
+        .map_err(|e| e.to_string())?;
+        let duration = HistogramVec::new(
+            HistogramOpts::new(
+                "hj2_import_duration_seconds",
+                "Time spent detecting, validating and mapping an upload",
+            )
+            .buckets(exponential_buckets(0.001, 2.0, 14).map_err(|e| e.to_string())?),
+            &["format"],
+        )
+        .map_err(|e| e.to_string())?;
+        let upload_bytes = HistogramVec::new(
+            HistogramOpts::new("hj2_import_upload_bytes", "Size of the uploaded feed")
+                .buckets(exponential_buckets(1024.0, 4.0, 10).map_err(|e| e.to_string())?),
+            &["format"],
+        )
+        .map_err(|e| e.to_string())?;
+        let jobs = IntGaugeVec::new(
+            Opts::new(
+                "hj2_import_jobs",
+                "Jobs produced by the last import of a format",
+            ),
+            &["format"],
+        )
+        .map_err(|e| e.to_string())?;
+        let jobs_total = IntCounterVec::new(
+            Opts::new(
+                "hj2_import_jobs_total",
+                "Jobs produced by all imports of a format",
+            ),
+            &["format"],
+        )
+        .map_err(|e| e.to_string())?;
+
+        let registry = Registry::new();
+        for collector in [
+            Box::new(imports.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(duration.clone()),
+            Box::new(upload_bytes.clone()),
+            Box::new(jobs.clone()),
+            Box::new(jobs_total.clone()),
+        ] {
+            registry.register(collector).map_err(|e| e.to_string())?;
+        }
+
+        Ok(Self {
+            registry,
+            imports,
+            duration,
+            upload_bytes,
+            jobs,
+            jobs_total,
+        })
+    }
+
+    /// Record one finished import, `outcome` is `ok` or the error kind
+    /// Duration and size are left out for requests that were not processed or had no file
+    pub fn observe_import(
+        &self,
+        format: &str,
+        outcome: &str,
+        seconds: Option<f64>,
+        bytes: Option<usize>,
+        jobs: usize,
+    ) {
+        self.imports.with_label_values(&[format, outcome]).inc();
+        if let Some(seconds) = seconds {
+            self.duration.with_label_values(&[format]).observe(seconds);
+        }
+        if let Some(bytes) = bytes {
+            self.upload_bytes
+                .with_label_values(&[format])
+                .observe(bytes as f64);
+        }
+
+        if outcome == "ok" {
+            self.jobs.with_label_values(&[format]).set(jobs as i64);
+            self.jobs_total
+                .with_label_values(&[format])
+                .inc_by(jobs as u64);
+        }
+    }
+
+    /// Text exposition format
+    pub fn render(&self) -> Result<String, String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .map_err(|e| e.to_string())?;
+
+        String::from_utf8(buffer).map_err(|e| e.to_string())
+    }
+}
@@ -1,8 +1,21 @@
+pub mod category;
 pub mod common;
-pub mod parse_xmlgrandio;
+pub mod detect;
+pub mod duplicates;
+pub mod employment;
+pub mod fields;
+pub mod html;
+pub mod language;
+pub mod lenient;
+pub mod mapping;
+pub mod merge;
+pub mod metrics;
 pub mod parse_xmlhotelleriejobs;
 pub mod parse_xmlicims;
-pub mod parse_xmlpscout;
-pub mod parse_xmltidan;
-pub mod parse_xmlzohoquintessence;
-pub mod parse_xmlzohorecruit;
+pub mod pipeline;
+pub mod province;
+pub mod registry;
+pub mod schemas;
+pub mod text;
+pub mod validation;
+pub mod worker_pool;
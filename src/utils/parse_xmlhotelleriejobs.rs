@@ -1,176 +1,198 @@
-use std::collections::HashMap;
+use libxml::tree::{Document, Node};
 
-use axum::body::Bytes;
-use libxml::tree::Document;
+use crate::utils::{
+    common::{Company, FieldReport, Job, Location, Translation},
+    detect::has_nodes,
+    fields::{self, Record},
+    language::LanguageSource,
+    registry::FeedParser,
+};
 
-use crate::utils::common::{Company, Job, Translation};
+pub struct HotellerieJobsParser;
 
-use crate::utils::common;
+impl FeedParser for HotellerieJobsParser {
+    fn name(&self) -> &str {
+        "xml-hotelleriejobs"
+    }
 
-pub fn parse(file: &Bytes) -> Result<Vec<Job>, common::ParseError> {
-    let document = common::validate_against_xsd(file, "xsd-schemas/xml-hotelleriejobs.xsd");
-    if let Err(errors) = document {
-        return Err(common::ParseError {
-            message: "File is not valid".to_string(),
-            xml_errors: errors,
-        });
+    fn xsd_file(&self) -> &str {
+        "xml-hotelleriejobs.xsd"
     }
 
-    match parse_into_jobs(&document.unwrap()) {
-        Ok(jobs) => {
-            return Ok(jobs);
-        }
-        Err(e) => {
-            return Err(common::ParseError {
-                message: e,
-                xml_errors: vec![],
-            });
+    fn parse_into_jobs(&self, document: &Document) -> Result<Vec<Job>, String> {
+        parse_into_jobs(document)
+    }
+
+    fn field_report(&self, document: &Document) -> FieldReport {
+        let mapped = fields::mapped_by(map_job);
+        fields::report(self.name(), document, self.records_xpath(), None, &mapped)
+    }
+
+    fn records_xpath(&self) -> &str {
+        "job"
+    }
+
+    fn id_xpath(&self) -> &str {
+        "unique_id"
+    }
+
+    fn sniff(&self, root: &Node) -> u32 {
+        if has_nodes(root, "job/unique_id") {
+            90
+        } else {
+            0
         }
     }
 }
 
 fn parse_into_jobs(document: &Document) -> Result<Vec<Job>, String> {
-    let root = document.get_root_element().unwrap();
+    let root = document
+        .get_root_element()
+        .ok_or("Document has no root element")?;
 
     // Get <job> nodes
     let children = root
         .findnodes("job")
         .map_err(|e| format!("Error finding job nodes: {:?}", e))?;
-    let jobs = children
+    children
         .iter()
-        .map(|job| {
-            // Populate a dictionary, keyed by nodes names
-            // This will be used to populate the Job struct with unique fields only as the repeated fields are overwritten
-            let mut dictionary = HashMap::new();
-            let mut child = job.get_first_child();
-            while let Some(current_child) = child {
-                dictionary.insert(
-                    current_child.get_name().to_string(),
-                    current_child.get_content().clone().to_string(),
-                );
-                child = current_child.get_next_sibling();
-            }
-
-            let titles = job.findnodes("title").unwrap();
-            let descriptions = job.findnodes("description").unwrap();
-            let requirements = job.findnodes("requirements").unwrap();
-
-            // Build translations by language
-            let mut translations: Vec<Translation> = Vec::new();
-
-            for title_node in titles {
-                let locale_node = dictionary.get("jobCode");
-
-                let lang = match locale_node {
-                    Some(code) if code.to_lowercase().contains("fr") => String::from("fr"),
-                    _ => String::from("en"),
-                };
-                let title_content = title_node.get_content().to_string();
-
-                // Find existing translation with this language or create new one
-                let translation = translations
-                    .iter_mut()
-                    .find(|t| t.language == lang.to_string());
-                if let Some(existing_translation) = translation {
-                    existing_translation.title = title_content;
-                } else {
-                    translations.push(Translation {
-                        language: lang,
-                        title: title_content,
-                        description: String::new(),
-                        requirements: String::new(),
-                    });
-                }
-            }
-
-            for desc_node in descriptions {
-                let lang = desc_node.get_attribute("lang").unwrap_or("en".to_string());
-                let desc_content = desc_node.get_content().to_string();
-
-                // Find existing translation with this language or create new one
-                let translation = translations.iter_mut().find(|t| t.language == lang);
-                if let Some(existing_translation) = translation {
-                    existing_translation.description = desc_content;
-                } else {
-                    translations.push(Translation {
-                        language: lang,
-                        title: String::new(),
-                        description: desc_content,
-                        requirements: String::new(),
-                    });
-                }
-            }
-
-            for req_node in requirements {
-                let lang = req_node.get_attribute("lang").unwrap_or("en".to_string());
-                let req_content = req_node.get_content().to_string();
-
-                // Find existing translation with this language or create new one
-                let translation = translations.iter_mut().find(|t| t.language == lang);
-                if let Some(existing_translation) = translation {
-                    existing_translation.requirements = req_content;
-                } else {
-                    translations.push(Translation {
-                        language: lang,
-                        title: String::new(),
-                        description: String::new(),
-                        requirements: req_content,
-                    });
-                }
-            }
-
-            Job {
-                id: dictionary
-                    .get("unique_id")
-                    .unwrap_or(&String::new())
-                    .to_string(),
-                schedule: dictionary
-                    .get("schedule")
-                    .unwrap_or(&String::new())
-                    .to_string(),
-                category: dictionary
-                    .get("category")
-                    .unwrap_or(&String::new())
-                    .to_string(),
-                city: dictionary.get("city").unwrap_or(&String::new()).to_string(),
-                province: dictionary
-                    .get("province")
-                    .unwrap_or(&String::new())
-                    .to_string(),
-                application_method: dictionary
-                    .get("application_method")
-                    .unwrap_or(&String::new())
-                    .to_string(),
-                application_destination: dictionary
-                    .get("application_destination")
-                    .unwrap_or(&String::new())
-                    .to_string(),
-                company: Company {
-                    id: dictionary
-                        .get("company_id")
-                        .unwrap_or(&String::new())
-                        .to_string(),
-                    name: dictionary
-                        .get("company")
-                        .unwrap_or(&String::new())
-                        .to_string(),
-                    city: dictionary
-                        .get("company_city")
-                        .unwrap_or(&String::new())
-                        .to_string(),
-                    postal_code: dictionary
-                        .get("company_postal_code")
-                        .unwrap_or(&String::new())
-                        .to_string(),
-                    logo_url: dictionary
-                        .get("company_logo_url")
-                        .unwrap_or(&String::new())
-                        .to_string(),
-                },
-                translations: translations,
-            }
-        })
-        .collect();
-
-    Ok(jobs)
+        .map(|job| map_job(&Record::new(job)))
+        .collect::<Result<Vec<Job>, String>>()
+}
+
+/// The record holds unique fields only as the repeated fields are overwritten, translations
+/// are read from the repeated nodes
+fn map_job(record: &Record) -> Result<Job, String> {
+    // Build translations by language
+    let mut translations: Vec<Translation> = Vec::new();
+
+    for title_node in record.find("title")? {
+        let (lang, language_source) = node_language(&title_node);
+        let title_content = title_node.get_content().to_string();
+
+        // Find existing translation with this language or create new one
+        let translation = translations.iter_mut().find(|t| t.language == lang);
+        if let Some(existing_translation) = translation {
+            existing_translation.title = title_content;
+        } else {
+            translations.push(Translation {
+                language: lang,
+                language_source,
+                title: title_content,
+                description: String::new(),
+                requirements: String::new(),
+                ..Default::default()
+            });
+        }
+    }
+
+    for desc_node in record.find("description")? {
+        let (lang, language_source) = node_language(&desc_node);
+        let desc_content = desc_node.get_content().to_string();
+
+        // Find existing translation with this language or create new one
+        let translation = translations.iter_mut().find(|t| t.language == lang);
+        if let Some(existing_translation) = translation {
+            existing_translation.description = desc_content;
+        } else {
+            translations.push(Translation {
+                language: lang,
+                language_source,
+                title: String::new(),
+                description: desc_content,
+                requirements: String::new(),
+                ..Default::default()
+            });
+        }
+    }
+
+    for req_node in record.find("requirements")? {
+        let (lang, language_source) = node_language(&req_node);
+        let req_content = req_node.get_content().to_string();
+
+        // Find existing translation with this language or create new one
+        let translation = translations.iter_mut().find(|t| t.language == lang);
+        if let Some(existing_translation) = translation {
+            existing_translation.requirements = req_content;
+        } else {
+            translations.push(Translation {
+                language: lang,
+                language_source,
+                title: String::new(),
+                description: String::new(),
+                requirements: req_content,
+                ..Default::default()
+            });
+        }
+    }
+
+    let city = record.get("city");
+    let province = record.get("province");
+    Ok(Job {
+        id: record.get("unique_id"),
+        schedule: record.get("schedule"),
+        category: record.get("category"),
+        locations: Location::split_cities(
+            &city,
+            Location {
+                province: province.clone(),
+                ..Default::default()
+            },
+        ),
+        city,
+        province,
+        application_method: record.get("application_method"),
+        application_destination: record.get("application_destination"),
+        company: Company {
+            id: record.get("company_id"),
+            name: record.get("company"),
+            city: record.get("company_city"),
+            postal_code: record.get("company_postal_code"),
+            logo_url: record.get("company_logo_url"),
+        },
+        translations,
+        ..Default::default()
+    })
+}
+
+/// Language from the `lang` attribute, empty without one so the pipeline detects it
+fn node_language(node: &Node) -> (String, LanguageSource) {
+    match node
+        .get_attribute("lang")
+        .map(|lang| lang.trim().to_lowercase())
+    {
+        Some(lang) if !lang.is_empty() => (lang, LanguageSource::Attribute),
+        _ => (String::new(), LanguageSource::Default),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libxml::parser::Parser;
+
+    use super::*;
+
+    #[test]
+    fn field_report_lists_every_field_the_mapping_reads() {
+        let document = Parser::default()
+            .parse_string(
+                "<source><job><unique_id>HJ-1</unique_id><schedule>Temps plein</schedule>\
+                 <category>Tourism</category><city>Québec</city><province>QC</province>\
+                 <application_method>url</application_method>\
+                 <application_destination>https://hj.example/1</application_destination>\
+                 <company_id>7</company_id><company>Château</company>\
+                 <company_city>Québec</company_city><company_postal_code>G1R 4P5\
+                 </company_postal_code><company_logo_url>https://hj.example/logo.png\
+                 </company_logo_url><title lang=\"fr\">Guide</title>\
+                 <description lang=\"fr\">Guider</description>\
+                 <requirements lang=\"fr\">Bilingue</requirements>\
+                 <salary>20</salary></job></source>",
+            )
+            .unwrap();
+        let report = HotellerieJobsParser.field_report(&document);
+
+        assert_eq!(report.mapped.len(), 15);
+        let ignored: Vec<&str> = report.ignored.iter().map(|f| f.field.as_str()).collect();
+        assert_eq!(ignored, ["salary"]);
+    }
 }
@@ -1,35 +1,331 @@
-use axum::body::Bytes;
-use libxml::tree::Document;
+use libxml::tree::{Document, Node};
 
-use crate::utils::common::{self, Job};
+use crate::utils::{
+    common::{Company, FieldReport, Job, Location, Translation},
+    detect::has_nodes,
+    fields::{self, Record},
+    language::LanguageSource,
+    registry::FeedParser,
+};
 
-pub fn parse(file: &Bytes) -> Result<Vec<Job>, common::ParseError> {
-    let document = common::validate_against_xsd(file, "xsd-schemas/xml-icims.xsd");
+pub struct IcimsParser;
 
-    if let Err(errors) = document {
-        return Err(common::ParseError {
-            message: "File is not valid".to_string(),
-            xml_errors: errors,
-        });
+impl FeedParser for IcimsParser {
+    fn name(&self) -> &str {
+        "xml-icims"
     }
 
-    match parse_into_jobs(&document.unwrap()) {
-        Ok(jobs) => Ok(jobs),
-        Err(e) => Err(common::ParseError {
-            message: e,
-            xml_errors: vec![],
-        }),
+    fn xsd_file(&self) -> &str {
+        "xml-icims.xsd"
+    }
+
+    fn parse_into_jobs(&self, document: &Document) -> Result<Vec<Job>, String> {
+        parse_into_jobs(document)
+    }
+
+    fn field_report(&self, document: &Document) -> FieldReport {
+        let mapped = fields::mapped_by(map_job);
+        fields::report(self.name(), document, self.records_xpath(), None, &mapped)
+    }
+
+    fn records_xpath(&self) -> &str {
+        "job"
+    }
+
+    fn id_xpath(&self) -> &str {
+        "id"
+    }
+
+    fn sniff(&self, root: &Node) -> u32 {
+        if root.get_name() == "jobs" && has_nodes(root, "job/id") && has_nodes(root, "job/location")
+        {
+            90
+        } else {
+            0
+        }
     }
 }
 
 /*
  * In this format language is included in id field as suffix after union trait
- * This means that for our format we need to list unique ids without language suffixes then merge jobs languages
+ * This means that for our format each record is one language of a job, the pipeline merges
+ * records whose ids only differ by that suffix (merge_translations)
  */
 fn parse_into_jobs(document: &Document) -> Result<Vec<Job>, String> {
-    let root = document.get_root_element().unwrap();
-    let children = root.get_child_elements();
-    let jobs = vec![];
+    let root = document
+        .get_root_element()
+        .ok_or("Document has no root element")?;
+    let children = root
+        .findnodes("job")
+        .map_err(|e| format!("Error finding job nodes: {:?}", e))?;
+
+    children
+        .iter()
+        .map(|job| map_job(&Record::new(job)))
+        .collect()
+}
+
+/// One language variant of a job, the id keeps its language suffix for the merge step
+fn map_job(record: &Record) -> Result<Job, String> {
+    // Only Québec jobs are imported, enforced by the allowed_provinces policy
+    let state = find_content(record, "location/state")?.trim().to_string();
+
+    let id = record.get("id").trim().to_string();
+    let (_, language) = split_language_suffix(&id);
+    let language_source = if language.is_empty() {
+        LanguageSource::Default
+    } else {
+        LanguageSource::Code
+    };
+
+    let raw_description = record.get("description");
+    let (metadata, description) = extract_hidden_metadata(&raw_description);
+
+    let translation = Translation {
+        language,
+        language_source,
+        title: record.get("title"),
+        description,
+        ..Default::default()
+    };
+
+    let mut city = find_content(record, "location/city")?.trim().to_string();
+    if city.is_empty() {
+        city = metadata_value(&metadata, &["Ville", "City", "Lieu", "Location"]);
+    }
+
+    let categories = record
+        .find("categories/category")?
+        .iter()
+        .flat_map(|category| {
+            category
+                .get_content()
+                .split(" & ")
+                .map(|c| c.trim().to_string())
+                .collect::<Vec<String>>()
+        })
+        .filter(|c| !c.is_empty())
+        .collect::<Vec<String>>();
+
+    Ok(Job {
+        id,
+        schedule: metadata_value(&metadata, &["Horaire", "Schedule"]),
+        category: categories.join(","),
+        locations: Location::split_cities(
+            &city,
+            Location {
+                province: state.clone(),
+                ..Default::default()
+            },
+        ),
+        city,
+        province: state,
+        application_method: "url".to_string(),
+        application_destination: record.get("url"),
+        company: Company {
+            name: record.get("brand"),
+            ..Default::default()
+        },
+        translations: vec![translation],
+        ..Default::default()
+    })
+}
+
+fn find_content(record: &Record, xpath: &str) -> Result<String, String> {
+    Ok(record
+        .find(xpath)?
+        .first()
+        .map(|n| n.get_content())
+        .unwrap_or_default())
+}
+
+/// Split `12345-fr` into (`12345`, `fr`), the language is empty for ids without a known
+/// suffix so the pipeline detects it
+fn split_language_suffix(raw_id: &str) -> (String, String) {
+    let raw_id = raw_id.trim();
+    match raw_id.rsplit_once('-') {
+        Some((id, lang)) if lang.eq_ignore_ascii_case("fr") || lang.eq_ignore_ascii_case("en") => {
+            (id.to_string(), lang.to_lowercase())
+        }
+        _ => (raw_id.to_string(), String::new()),
+    }
+}
+
+/// Pull the `<div class="hide-me">` block out of the description
+/// Returns the metadata as (label, value) pairs and the description without the block
+fn extract_hidden_metadata(description: &str) -> (Vec<(String, String)>, String) {
+    let Some(start) = description.find("<div class=\"hide-me\">") else {
+        return (vec![], description.trim().to_string());
+    };
+    let content_start = start + "<div class=\"hide-me\">".len();
+    let Some(length) = description[content_start..].find("</div>") else {
+        return (vec![], description.trim().to_string());
+    };
+    let content_end = content_start + length;
+
+    let metadata = description[content_start..content_end]
+        .split("<br")
+        .filter_map(|entry| {
+            // Drop what remains of the <br>, <br/> or <br /> tag
+            let entry = entry.trim_start_matches(['/', ' ', '>']);
+            let (label, value) = entry.split_once("</b>")?;
+            Some((
+                strip_tags(label).trim().to_string(),
+                strip_tags(value).trim().to_string(),
+            ))
+        })
+        .collect();
+
+    let remaining = description[..start].to_string() + &description[content_end + "</div>".len()..];
 
-    Ok(jobs)
+    (metadata, remaining.trim().to_string())
+}
+
+fn metadata_value(metadata: &[(String, String)], labels: &[&str]) -> String {
+    metadata
+        .iter()
+        .find(|(label, _)| labels.iter().any(|l| label.eq_ignore_ascii_case(l)))
+        .map(|(_, value)| value.clone())
+        .unwrap_or_default()
+}
+
+fn strip_tags(html: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use libxml::parser::Parser;
+
+    use super::*;
+
+    fn jobs(job: &str) -> Vec<Job> {
+        let document = Parser::default()
+            .parse_string(format!("<jobs>{}</jobs>", job))
+            .unwrap();
+        parse_into_jobs(&document).unwrap()
+    }
+
+    fn pairs(metadata: &[(&str, &str)]) -> Vec<(String, String)> {
+        metadata
+            .iter()
+            .map(|(label, value)| (label.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn hidden_metadata_accepts_every_br_style() {
+        let (metadata, description) = extract_hidden_metadata(
+            "<div class=\"hide-me\"><b>Horaire</b> Temps plein<br><b>Ville</b> Montréal<br/>\
+             <b>Lieu</b> <i>Hôtel</i><br /></div><p>Préparer des cocktails.</p>",
+        );
+        assert_eq!(
+            metadata,
+            pairs(&[
+                ("Horaire", "Temps plein"),
+                ("Ville", "Montréal"),
+                ("Lieu", "Hôtel")
+            ])
+        );
+        assert_eq!(description, "<p>Préparer des cocktails.</p>");
+    }
+
+    #[test]
+    fn description_without_hidden_metadata_is_kept() {
+        let (metadata, description) = extract_hidden_metadata("  <p>Greet guests.</p> ");
+        assert!(metadata.is_empty());
+        assert_eq!(description, "<p>Greet guests.</p>");
+    }
+
+    #[test]
+    fn city_falls_back_to_the_metadata() {
+        let jobs = jobs(
+            "<job><id>555-fr</id><title>Barman</title>\
+             <description>&lt;div class=\"hide-me\"&gt;&lt;b&gt;Ville&lt;/b&gt; Montréal\
+             &lt;br&gt;&lt;/div&gt;&lt;p&gt;Cocktails&lt;/p&gt;</description>\
+             <location><city> </city><state>QC</state></location></job>",
+        );
+        assert_eq!(jobs[0].city, "Montréal");
+        assert_eq!(jobs[0].province, "QC");
+        assert_eq!(jobs[0].translations[0].description, "<p>Cocktails</p>");
+    }
+
+    #[test]
+    fn location_city_wins_over_the_metadata() {
+        let jobs = jobs(
+            "<job><id>555-fr</id><description>&lt;div class=\"hide-me\"&gt;&lt;b&gt;Ville\
+             &lt;/b&gt; Montréal&lt;br&gt;&lt;/div&gt;</description>\
+             <location><city>Laval</city></location></job>",
+        );
+        assert_eq!(jobs[0].city, "Laval");
+    }
+
+    #[test]
+    fn categories_are_split_on_ampersands() {
+        let jobs = jobs(
+            "<job><id>555-fr</id><categories><category>Food &amp; Beverage &amp; Bar</category>\
+             <category>Hospitality</category></categories></job>",
+        );
+        assert_eq!(jobs[0].category, "Food,Beverage,Bar,Hospitality");
+    }
+
+    #[test]
+    fn language_suffix_is_split_from_the_id() {
+        let split = split_language_suffix;
+        assert_eq!(split("555-fr"), ("555".to_string(), "fr".to_string()));
+        assert_eq!(split(" 555-EN "), ("555".to_string(), "en".to_string()));
+        assert_eq!(split("555"), ("555".to_string(), String::new()));
+        assert_eq!(split("555-de"), ("555-de".to_string(), String::new()));
+    }
+
+    #[test]
+    fn records_keep_the_raw_id_for_the_merge_step() {
+        let jobs = jobs("<job><id>555-en</id><title>Bartender</title></job>");
+        assert_eq!(jobs[0].id, "555-en");
+        assert_eq!(jobs[0].translations[0].language, "en");
+        assert_eq!(
+            jobs[0].translations[0].language_source,
+            LanguageSource::Code
+        );
+    }
+
+    #[test]
+    fn field_report_lists_every_field_the_mapping_reads() {
+        let document = Parser::default()
+            .parse_string(
+                "<jobs><job><id>555-fr</id><title>Barman</title><description>Cocktails\
+                 </description><url>https://icims.example/555</url><brand>Marriott</brand>\
+                 <location><city>Montréal</city><state>QC</state></location>\
+                 <categories><category>Bar</category></categories>\
+                 <postedDate>2025-01-01</postedDate></job></jobs>",
+            )
+            .unwrap();
+        let report = IcimsParser.field_report(&document);
+
+        let mapped: Vec<&str> = report.mapped.iter().map(|f| f.field.as_str()).collect();
+        assert_eq!(
+            mapped,
+            [
+                "brand",
+                "categories",
+                "description",
+                "id",
+                "location",
+                "title",
+                "url"
+            ]
+        );
+        let ignored: Vec<&str> = report.ignored.iter().map(|f| f.field.as_str()).collect();
+        assert_eq!(ignored, ["postedDate"]);
+    }
 }
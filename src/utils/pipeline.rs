@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+
+use crate::{
+    config::Config,
+    utils::{
+        category::CategoryMap,
+        common::{Job, ParsedFeed, SkippedJob, UnmappedLabel, Warning},
+        duplicates::{self, DuplicatePolicy},
+        employment, html, language,
+        merge::{self, MergeKey},
+        province::{self, ProvinceFilter},
+        text,
+        validation::{self, Severity},
+    },
+};
+
+/// Per-request settings sent by the client next to the file
+#[derive(Default)]
+pub struct ImportOptions {
+    /// Replaces the province filter configured for the format
+    pub provinces: Option<ProvinceFilter>,
+    pub duplicates: DuplicatePolicy,
+    /// Also treat jobs with the same title, company and city as duplicates
+    pub near_duplicates: bool,
+    /// Leave out the job records with schema errors instead of failing the whole file
+    pub lenient: bool,
+}
+
+/// Post-mapping steps shared by every format: fill the normalized fields of each job and
+/// collect what could not be understood
+pub struct Pipeline {
+    categories: CategoryMap,
+    provinces: HashMap<String, ProvinceFilter>,
+    merge_keys: HashMap<String, MergeKey>,
+}
+
+impl Pipeline {
+    /// `known_formats` lists every format key, used to check format-specific tables
+    pub fn new(config: &Config, known_formats: &[&str]) -> Result<Self, String> {
+        let mut provinces = HashMap::new();
+        for (format, list) in &config.allowed_provinces {
+            if !known_formats.contains(&format.as_str()) {
+                return Err(format!("allowed_provinces lists unknown format {}", format));
+            }
+            let filter = ProvinceFilter::parse(&list.join(","))
+                .map_err(|e| format!("allowed_provinces for {}: {}", format, e))?;
+            provinces.insert(format.clone(), filter);
+        }
+
+        if let Some(format) = config
+            .merge_translations
+            .keys()
+            .find(|format| !known_formats.contains(&format.as_str()))
+        {
+            return Err(format!(
+                "merge_translations lists unknown format {}",
+                format
+            ));
+        }
+
+        Ok(Self {
+            categories: CategoryMap::load(config.category_map.as_deref(), known_formats)?,
+            provinces,
+            merge_keys: config.merge_translations.clone(),
+        })
+    }
+
+    pub fn run(&self, format: &str, jobs: Vec<Job>, options: &ImportOptions) -> ParsedFeed {
+        let mut feed = ParsedFeed::default();
+        let filter = options
+            .provinces
+            .as_ref()
+            .or_else(|| self.provinces.get(format));
+
+        // Merged first so the rest of the pipeline sees one job per posting
+        let jobs = match self.merge_keys.get(format) {
+            Some(key) => merge::merge(
+                jobs,
+                *key,
+                |field, kept, other| self.same_value(format, field, kept, other),
+                &mut feed.merge_conflicts,
+            ),
+            None => jobs,
+        };
+        let jobs = duplicates::remove(
+            jobs,
+            options.duplicates,
+            options.near_duplicates,
+            &mut feed.duplicates,
+        );
+
+        for mut job in jobs {
+            for location in job.locations.iter_mut() {
+                if let Some(code) = province::normalize(&location.province) {
+                    location.province = code.to_string();
+                }
+            }
+
+            let code = province::normalize(&job.province);
+            match code {
+                Some(code) => job.province = code.to_string(),
+                None if !job.province.trim().is_empty() => feed.warnings.push(Warning {
+                    job_id: job.id.clone(),
+                    field: "province",
+                    message: format!("Unrecognized province {:?}", job.province),
+                }),
+                None => {}
+            }
+
+            if let Some(filter) = filter
+                && !filter.allows(code)
+            {
+                feed.skipped.push(SkippedJob {
+                    reason: match code {
+                        Some(code) => format!("Province {} is outside the import area", code),
+                        None => "Province is unknown, the import is restricted to some provinces"
+                            .to_string(),
+                    },
+                    job_id: job.id,
+                });
+                continue;
+            }
+
+            let (types, unrecognized) = employment::normalize(&job.schedule);
+            job.employment_types = types;
+            feed.warnings
+                .extend(unrecognized.into_iter().map(|token| Warning {
+                    job_id: job.id.clone(),
+                    field: "schedule",
+                    message: format!("Unrecognized schedule value {:?}", token),
+                }));
+
+            let (categories, unmapped) = self.categories.map(format, &job.category);
+            job.categories = categories;
+            for label in unmapped {
+                UnmappedLabel::count(&mut feed.unmapped_categories, label);
+            }
+
+            for translation in job.translations.iter_mut() {
+                let description = html::sanitize(&translation.description);
+                translation.description = description.html;
+                translation.description_text = description.text;
+                translation.description_markdown = description.markdown;
+
+                let requirements = html::sanitize(&translation.requirements);
+                translation.requirements = requirements.html;
+                translation.requirements_text = requirements.text;
+                translation.requirements_markdown = requirements.markdown;
+
+                // Feeds that do not label their translations are identified from the text
+                if translation.language.is_empty() {
+                    let sample = format!("{} {}", translation.title, translation.description_text);
+                    let (language, source, confidence) = language::identify(&sample);
+                    translation.language = language.to_string();
+                    translation.language_source = source;
+                    translation.language_confidence = confidence;
+                } else {
+                    translation.language_confidence = 1.0;
+                }
+            }
+
+            let issues = validation::validate(&job);
+            let invalid = issues.iter().any(|issue| issue.severity == Severity::Error);
+            feed.validation_issues.extend(issues);
+            if invalid {
+                continue;
+            }
+
+            feed.jobs.push(job);
+        }
+
+        feed
+    }
+
+    /// Whether two values of a shared field name the same thing once normalized, records of
+    /// one posting often write them in their own language
+    fn same_value(&self, format: &str, field: &str, kept: &str, other: &str) -> bool {
+        match field {
+            "province" => province::normalize(kept)
+                .is_some_and(|code| province::normalize(other) == Some(code)),
+            "schedule" => {
+                let (kept, kept_unrecognized) = employment::normalize(kept);
+                let (other, other_unrecognized) = employment::normalize(other);
+                kept_unrecognized.is_empty()
+                    && other_unrecognized.is_empty()
+                    && kept.iter().all(|value| other.contains(value))
+                    && other.iter().all(|value| kept.contains(value))
+            }
+            "category" => {
+                let (kept, kept_unmapped) = self.categories.map(format, kept);
+                let (other, other_unmapped) = self.categories.map(format, other);
+                kept_unmapped.is_empty()
+                    && other_unmapped.is_empty()
+                    && kept.iter().all(|value| other.contains(value))
+                    && other.iter().all(|value| kept.contains(value))
+            }
+            _ => text::fold(kept) == text::fold(other),
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use crate::utils::text;
+
+/// ISO 3166-2:CA code of every province and territory with the spellings feeds use for it,
+/// folded with `text::fold`
+const PROVINCES: &[(&str, &[&str])] = &[
+    ("CA-AB", &["ab", "alta", "alberta"]),
+    (
+        "CA-BC",
+        &["bc", "cb", "british columbia", "colombie britannique"],
+    ),
+    ("CA-MB", &["mb", "man", "manitoba"]),
+    ("CA-NB", &["nb", "new brunswick", "nouveau brunswick"]),
+    (
+        "CA-NL",
+        &[
+            "nl",
+            "nf",
+            "nfld",
+            "tnl",
+            "newfoundland",
+            "newfoundland and labrador",
+            "terre neuve",
+            "terre neuve et labrador",
+        ],
+    ),
+    ("CA-NS", &["ns", "n e", "nova scotia", "nouvelle ecosse"]),
+    (
+        "CA-NT",
+        &[
+            "nt",
+            "nwt",
+            "tno",
+            "t n o",
+            "northwest territories",
+            "territoires du nord ouest",
+        ],
+    ),
+    ("CA-NU", &["nu", "nvt", "nunavut"]),
+    ("CA-ON", &["on", "ont", "ontario"]),
+    (
+        "CA-PE",
+        &[
+            "pe",
+            "pei",
+            "p e i",
+            "ipe",
+            "i p e",
+            "prince edward island",
+            "ile du prince edouard",
+        ],
+    ),
+    // QB and PQ are not official but show up in iCIMS and older feeds
+    (
+        "CA-QC",
+        &["qc", "qb", "pq", "que", "quebec", "province de quebec"],
+    ),
+    ("CA-SK", &["sk", "sask", "saskatchewan"]),
+    ("CA-YT", &["yt", "yk", "yukon", "yukon territory"]),
+];
+
+/// ISO 3166-2:CA code ("CA-QC") for a province name, abbreviation or code, `None` when the
+/// value is not a Canadian province or territory
+pub fn normalize(raw: &str) -> Option<&'static str> {
+    let folded = text::fold(raw);
+    // Already a full code, "CA-QC" folds to "ca qc"
+    let folded = folded.strip_prefix("ca ").unwrap_or(&folded);
+
+    PROVINCES
+        .iter()
+        .find(|(_, spellings)| spellings.contains(&folded))
+        .map(|(code, _)| *code)
+}
+
+/// Which provinces an import keeps
+#[derive(Clone, Debug)]
+pub enum ProvinceFilter {
+    Any,
+    Only(Vec<&'static str>),
+}
+
+impl ProvinceFilter {
+    /// Parse a comma-separated list of provinces ("QC,ON", "Québec"), `*` or `any` keep
+    /// every job
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        if value == "*" || value.eq_ignore_ascii_case("any") {
+            return Ok(Self::Any);
+        }
+
+        Self::from_list(value.split(','))
+    }
+
+    pub fn from_list<'a>(provinces: impl IntoIterator<Item = &'a str>) -> Result<Self, String> {
+        let codes = provinces
+            .into_iter()
+            .map(|province| {
+                normalize(province).ok_or_else(|| format!("{:?} is not a province", province))
+            })
+            .collect::<Result<Vec<&'static str>, String>>()?;
+        if codes.is_empty() {
+            return Err("Province filter is empty".to_string());
+        }
+
+        Ok(Self::Only(codes))
+    }
+
+    pub fn allows(&self, code: Option<&str>) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Only(codes) => code.is_some_and(|code| codes.contains(&code)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quebec_spellings_normalize_to_its_code() {
+        for spelling in ["Québec", "Quebec", "QC", "QB", "CA-QC", " qc ", "PQ"] {
+            assert_eq!(normalize(spelling), Some("CA-QC"), "{}", spelling);
+        }
+    }
+
+    #[test]
+    fn other_provinces_and_unknown_values() {
+        assert_eq!(normalize("Colombie-Britannique"), Some("CA-BC"));
+        assert_eq!(normalize("Ont."), Some("CA-ON"));
+        assert_eq!(normalize("New York"), None);
+        assert_eq!(normalize(""), None);
+    }
+
+    #[test]
+    fn filter_parses_lists_and_wildcards() {
+        assert!(matches!(
+            ProvinceFilter::parse("*"),
+            Ok(ProvinceFilter::Any)
+        ));
+        assert!(matches!(
+            ProvinceFilter::parse(" ANY "),
+            Ok(ProvinceFilter::Any)
+        ));
+
+        let filter = ProvinceFilter::parse("Québec, ON").unwrap();
+        assert!(filter.allows(Some("CA-QC")));
+        assert!(filter.allows(Some("CA-ON")));
+        assert!(!filter.allows(Some("CA-BC")));
+        assert!(!filter.allows(None));
+    }
+
+    #[test]
+    fn filter_rejects_unknown_provinces() {
+        assert_eq!(
+            ProvinceFilter::parse("QC,Texas").err(),
+            Some("\"Texas\" is not a province".to_string())
+        );
+        assert!(ProvinceFilter::parse("").is_err());
+    }
+}
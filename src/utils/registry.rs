@@ -0,0 +1,266 @@
+use std::path::Path;
+
+use axum::body::Bytes;
+use libxml::{
+    parser::Parser,
+    tree::{Document, Node},
+};
+
+use crate::{
+    config::Config,
+    utils::{
+        common::{self, FieldReport, Job, LabelReport, ParseError, ParseErrorKind, RejectedJob},
+        lenient, mapping,
+        parse_xmlhotelleriejobs::HotellerieJobsParser,
+        parse_xmlicims::IcimsParser,
+        schemas::SchemaCache,
+        text,
+    },
+};
+
+/// A feed format that can be imported through the `/import` endpoint
+pub trait FeedParser: Send + Sync {
+    /// Format key sent by clients in the `format` multipart field (e.g. `xml-tidan`)
+    fn name(&self) -> &str;
+
+    /// File name, inside the schema directory, of the XSD the document is validated against
+    fn xsd_file(&self) -> &str;
+
+    /// Map a schema-valid document into jobs
+    fn parse_into_jobs(&self, document: &Document) -> Result<Vec<Job>, String>;
+
+    /// XPath, relative to the root element, of the job records
+    fn records_xpath(&self) -> &str;
+
+    /// XPath, relative to a job record, of the element holding its id
+    /// Names the records a lenient import rejects before they are mapped
+    fn id_xpath(&self) -> &str;
+
+    /// Values of the key attribute the mapping looks up, checked at startup against the
+    /// schema when it enumerates them so a misspelled name cannot ship
+    fn field_names(&self) -> Vec<&str> {
+        vec![]
+    }
+
+    /// Source fields of the job records of a schema-valid document, split between the ones the
+    /// mapping reads and the ones it ignores, usually through `fields::report`
+    ///
+    /// Rust parsers list the fields they read with `fields::mapped_by`, which runs their record
+    /// mapping over an empty `fields::Record`. Their mapping must therefore look up every field
+    /// whatever the record holds, a lookup behind a condition on another value goes unreported.
+    fn field_report(&self, document: &Document) -> FieldReport;
+
+    /// How the field labels of a schema-valid document matched the mapping, for formats keying
+    /// their fields by a label written by hand
+    fn match_labels(&self, _document: &Document) -> LabelReport {
+        LabelReport::default()
+    }
+
+    /// Client profiles adjusting the mapping of this format
+    /// Only formats described by a mapping file have profiles
+    fn profiles(&self) -> Vec<&str> {
+        vec![]
+    }
+
+    /// The parser of this format with the overrides of a profile, `None` if it has no such
+    /// profile
+    /// It keeps the format name so the schema and format-specific settings still apply
+    fn profile(&self, _name: &str) -> Option<&dyn FeedParser> {
+        None
+    }
+
+    /// Score from 0 to 100 of how much the document root looks like this format,
+    /// used to detect the format when the client does not send one
+    fn sniff(&self, _root: &Node) -> u32 {
+        0
+    }
+
+    /// Whether the format can currently be imported
+    fn is_implemented(&self) -> bool {
+        true
+    }
+
+    /// Validate then map the upload
+    /// When `lenient`, records with schema errors are left out and returned next to the jobs
+    /// instead of failing the whole file
+    fn parse(
+        &self,
+        file: &Bytes,
+        schemas: &SchemaCache,
+        lenient: bool,
+    ) -> Result<ParsedDocument, ParseError> {
+        let document = common::parse_document(file)?;
+        let rejected = match schemas.validate_document(&document, self.name()) {
+            Ok(()) => vec![],
+            Err(error) if lenient && error.kind == ParseErrorKind::SchemaInvalid => {
+                let rejected = lenient::reject_invalid_records(
+                    &document,
+                    error,
+                    self.records_xpath(),
+                    self.id_xpath(),
+                )?;
+                // What is left must be valid on its own, e.g. a feed requiring at least one job
+                schemas.validate_document(&document, self.name())?;
+                rejected
+            }
+            Err(error) => return Err(error),
+        };
+
+        let jobs = self
+            .parse_into_jobs(&document)
+            .map_err(|e| ParseError::new(ParseErrorKind::MappingFailed, e))?;
+
+        Ok(ParsedDocument {
+            jobs,
+            rejected,
+            labels: self.match_labels(&document),
+            fields: self.field_report(&document),
+        })
+    }
+}
+
+/// What a parser got out of an upload, before the import pipeline runs
+pub struct ParsedDocument {
+    pub jobs: Vec<Job>,
+    /// Records left out by a lenient import
+    pub rejected: Vec<RejectedJob>,
+    pub labels: LabelReport,
+    pub fields: FieldReport,
+}
+
+/// Lookup table of every known feed format, keyed by format name
+pub struct Registry {
+    parsers: Vec<Box<dyn FeedParser>>,
+    schemas: SchemaCache,
+    /// Key of every format, enabled or not
+    known: Vec<String>,
+}
+
+impl Registry {
+    /// Register every enabled format and compile their schemas
+    /// Fails if any schema is missing or invalid so the server never starts half-configured
+    pub fn new(config: &Config) -> Result<Self, String> {
+        let all_parsers = all_parsers(&config.mapping_dir)?;
+        let known: Vec<String> = all_parsers
+            .iter()
+            .map(|parser| parser.name().to_string())
+            .collect();
+
+        if let Some(formats) = &config.enabled_formats
+            && let Some(unknown) = formats
+                .iter()
+                .find(|format| !all_parsers.iter().any(|parser| parser.name() == *format))
+        {
+            return Err(format!("enabled_formats lists unknown format {}", unknown));
+        }
+
+        let parsers: Vec<Box<dyn FeedParser>> = all_parsers
+            .into_iter()
+            .filter(|parser| config.is_format_enabled(parser.name()))
+            .collect();
+        for parser in &parsers {
+            check_field_names(
+                parser.as_ref(),
+                &schema_path(&config.schema_dir, parser.as_ref()),
+            )?;
+        }
+        let schemas = SchemaCache::load(parsers.iter().map(|parser| {
+            (
+                parser.name(),
+                schema_path(&config.schema_dir, parser.as_ref()),
+            )
+        }))?;
+
+        Ok(Self {
+            parsers,
+            schemas,
+            known,
+        })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn FeedParser> {
+        self.parsers
+            .iter()
+            .find(|parser| parser.name() == name)
+            .map(|parser| parser.as_ref())
+    }
+
+    pub fn parsers(&self) -> impl Iterator<Item = &dyn FeedParser> {
+        self.parsers.iter().map(|parser| parser.as_ref())
+    }
+
+    pub fn schemas(&self) -> &SchemaCache {
+        &self.schemas
+    }
+
+    /// Key of every format the server knows about, enabled or not
+    pub fn known_formats(&self) -> Vec<&str> {
+        self.known.iter().map(|format| format.as_str()).collect()
+    }
+}
+
+/// Formats written in Rust, then the ones described by the mapping files of `mapping_dir`
+fn all_parsers(mapping_dir: &Path) -> Result<Vec<Box<dyn FeedParser>>, String> {
+    let mut parsers: Vec<Box<dyn FeedParser>> =
+        vec![Box::new(HotellerieJobsParser), Box::new(IcimsParser)];
+
+    for parser in mapping::load_dir(mapping_dir)? {
+        if parsers
+            .iter()
+            .any(|existing| existing.name() == parser.name())
+        {
+            return Err(format!(
+                "Mapping in {} redefines format {}",
+                mapping_dir.display(),
+                parser.name()
+            ));
+        }
+        parsers.push(Box::new(parser));
+    }
+
+    Ok(parsers)
+}
+
+fn schema_path(schema_dir: &Path, parser: &dyn FeedParser) -> String {
+    schema_dir
+        .join(parser.xsd_file())
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Fail when the parser looks up a field name its XSD does not enumerate
+/// Names are compared the way the mapping looks them up, schemas without enumerations do not
+/// restrict them
+fn check_field_names(parser: &dyn FeedParser, path: &str) -> Result<(), String> {
+    let names = parser.field_names();
+    if names.is_empty() {
+        return Ok(());
+    }
+
+    let schema = Parser::default()
+        .parse_file(path)
+        .map_err(|e| format!("Could not read schema {}: {:?}", path, e))?;
+    let allowed = schema
+        .get_root_element()
+        .ok_or_else(|| format!("Schema {} has no root element", path))?
+        .findvalues("//*[local-name()='enumeration']/@value")
+        .map_err(|_| format!("Could not read the enumerations of schema {}", path))?;
+
+    if allowed.is_empty() {
+        return Ok(());
+    }
+    let allowed: Vec<String> = allowed.iter().map(|value| text::label_key(value)).collect();
+
+    match names
+        .iter()
+        .find(|name| !allowed.contains(&text::label_key(name)))
+    {
+        Some(name) => Err(format!(
+            "{} reads field {:?} which {} does not allow",
+            parser.name(),
+            name,
+            path
+        )),
+        None => Ok(()),
+    }
+}
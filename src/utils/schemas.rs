@@ -0,0 +1,117 @@
+use std::{collections::HashMap, path::Path, sync::Mutex};
+
+use axum::body::Bytes;
+use libxml::{
+    schemas::{SchemaParserContext, SchemaValidationContext},
+    tree::Document,
+};
+
+use crate::utils::common::{self, ParseError, ParseErrorKind};
+
+/// A compiled schema ready to validate documents
+struct Validator(SchemaValidationContext);
+
+// A validation context is only ever used by the thread that checked it out of its pool,
+// libxml2 supports this as long as contexts are not shared concurrently
+unsafe impl Send for Validator {}
+
+/// Pool of validation contexts compiled from the same XSD
+/// Contexts are not reentrant, concurrent requests each check out their own and extra ones
+/// are compiled on demand then kept for later requests
+struct SchemaPool {
+    path: String,
+    validators: Mutex<Vec<Validator>>,
+}
+
+impl SchemaPool {
+    fn checkout(&self) -> Result<Validator, String> {
+        let cached = self.validators.lock().unwrap().pop();
+        match cached {
+            Some(validator) => Ok(validator),
+            None => compile(&self.path),
+        }
+    }
+
+    fn checkin(&self, validator: Validator) {
+        self.validators.lock().unwrap().push(validator);
+    }
+}
+
+/// Compiled XSD schemas keyed by format name, loaded once at startup and shared by every request
+pub struct SchemaCache {
+    pools: HashMap<String, SchemaPool>,
+}
+
+impl SchemaCache {
+    /// Compile every (format, schema path) pair, failing on the first schema that is missing
+    /// or invalid
+    pub fn load<'a>(schemas: impl IntoIterator<Item = (&'a str, String)>) -> Result<Self, String> {
+        let mut pools = HashMap::new();
+        for (format, path) in schemas {
+            let validator = compile(&path)?;
+            pools.insert(
+                format.to_string(),
+                SchemaPool {
+                    path,
+                    validators: Mutex::new(vec![validator]),
+                },
+            );
+        }
+
+        Ok(Self { pools })
+    }
+
+    /// Make sure a validation context for the format is available, compiling one if every
+    /// cached context is busy
+    pub fn check(&self, format: &str) -> Result<(), String> {
+        let pool = self
+            .pools
+            .get(format)
+            .ok_or_else(|| format!("Schema for {} is not loaded", format))?;
+        pool.checkin(pool.checkout()?);
+
+        Ok(())
+    }
+
+    /// Parse the upload and validate it against the schema of the format
+    pub fn validate(&self, file: &Bytes, format: &str) -> Result<Document, ParseError> {
+        let document = common::parse_document(file)?;
+        self.validate_document(&document, format)?;
+
+        Ok(document)
+    }
+
+    pub fn validate_document(&self, document: &Document, format: &str) -> Result<(), ParseError> {
+        let pool = self.pools.get(format).ok_or_else(|| {
+            ParseError::new(
+                ParseErrorKind::Internal,
+                format!("Schema for {} is not loaded", format),
+            )
+        })?;
+        let mut validator = pool
+            .checkout()
+            .map_err(|e| ParseError::new(ParseErrorKind::Internal, e))?;
+
+        let result = common::validate_against_xsd(document, &mut validator.0);
+        pool.checkin(validator);
+
+        result
+    }
+}
+
+fn compile(path: &str) -> Result<Validator, String> {
+    if !Path::new(path).is_file() {
+        return Err(format!("Schema {} does not exist", path));
+    }
+
+    let mut schema_parser = SchemaParserContext::from_file(path);
+    SchemaValidationContext::from_parser(&mut schema_parser)
+        .map(Validator)
+        .map_err(|errors| {
+            let messages: Vec<String> = errors
+                .iter()
+                .map(|e| e.message.clone().unwrap_or_default().trim().to_string())
+                .collect();
+            format!("Schema {} is invalid: {}", path, messages.join("; "))
+        })
+}
@@ -0,0 +1,81 @@
+use unicode_normalization::{UnicodeNormalization, char::is_combining_mark};
+
+/// Lowercase, strip French accents and turn punctuation into single spaces so labels written
+/// by hand in the feeds compare equal ("Temps-Plein" and "temps plein")
+pub fn fold(value: &str) -> String {
+    let mut folded = String::with_capacity(value.len());
+    for c in value.chars().flat_map(char::to_lowercase) {
+        let c = match c {
+            'à' | 'â' | 'ä' => 'a',
+            'ç' => 'c',
+            'é' | 'è' | 'ê' | 'ë' => 'e',
+            'î' | 'ï' => 'i',
+            'ô' | 'ö' => 'o',
+            'ù' | 'û' | 'ü' => 'u',
+            'ÿ' => 'y',
+            c if c.is_alphanumeric() => c,
+            _ => ' ',
+        };
+        if c != ' ' || !folded.ends_with(' ') {
+            folded.push(c);
+        }
+    }
+
+    folded.trim().to_string()
+}
+
+/// Key under which a field label written by hand is looked up, so "État/Province",
+/// "Etat/province" and "Type d’emploi" match "Type d'emploi": compatibility forms and
+/// accents are dropped, quotes become plain apostrophes, case and spacing are ignored
+pub fn label_key(label: &str) -> String {
+    let mut key = String::with_capacity(label.len());
+    for c in label
+        .nfkd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+    {
+        let c = match c {
+            '\u{2018}' | '\u{2019}' | '\u{201B}' | '\u{02BC}' | '\u{00B4}' | '`' | '"' | '«'
+            | '»' | '\u{201C}' | '\u{201D}' => '\'',
+            c if c.is_whitespace() => ' ',
+            c => c,
+        };
+        if c != ' ' || !(key.is_empty() || key.ends_with(' ')) {
+            key.push(c);
+        }
+    }
+
+    key.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn label_key_ignores_accents_and_case() {
+        assert_eq!(label_key("État/Province"), label_key("Etat/province"));
+        assert_eq!(label_key("Secteur d'activité"), "secteur d'activite");
+    }
+
+    #[test]
+    fn label_key_folds_quotes_to_apostrophes() {
+        assert_eq!(label_key("Type d’emploi"), "type d'emploi");
+        assert_eq!(label_key("Type d`emploi"), "type d'emploi");
+        assert_eq!(label_key("«Titre»"), "'titre'");
+    }
+
+    #[test]
+    fn label_key_collapses_whitespace() {
+        assert_eq!(
+            label_key("  Titre de\u{00A0}la\t\tpublication \n"),
+            "titre de la publication"
+        );
+    }
+
+    #[test]
+    fn label_key_uses_compatibility_forms() {
+        assert_eq!(label_key("ﬁchier"), "fichier");
+        assert_eq!(label_key("Ｖｉｌｌｅ"), "ville");
+    }
+}
@@ -0,0 +1,307 @@
+use serde::Serialize;
+
+use crate::utils::{
+    common::{Job, Location, ValidationIssue},
+    text,
+};
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Severity {
+    /// The job cannot be published, it is left out of the import
+    Error,
+    /// The job is imported but something looks wrong
+    Warning,
+}
+
+/// A platform rule the XSD cannot express, adds an issue for each problem found in a job
+type Rule = fn(&Job, &mut Vec<ValidationIssue>);
+
+const RULES: &[Rule] = &[has_id, has_title, application_destination, postal_codes];
+
+/// Check a mapped job against every rule
+pub fn validate(job: &Job) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    for rule in RULES {
+        rule(job, &mut issues);
+    }
+    issues
+}
+
+fn issue(job: &Job, severity: Severity, field: &'static str, message: String) -> ValidationIssue {
+    ValidationIssue {
+        job_id: job.id.clone(),
+        severity,
+        field,
+        message,
+    }
+}
+
+fn has_id(job: &Job, issues: &mut Vec<ValidationIssue>) {
+    if job.id.trim().is_empty() {
+        issues.push(issue(
+            job,
+            Severity::Error,
+            "id",
+            "Job has no id".to_string(),
+        ));
+    }
+}
+
+fn has_title(job: &Job, issues: &mut Vec<ValidationIssue>) {
+    if !job
+        .translations
+        .iter()
+        .any(|translation| !translation.title.trim().is_empty())
+    {
+        issues.push(issue(
+            job,
+            Severity::Error,
+            "translations",
+            "Job has no translation with a title".to_string(),
+        ));
+    }
+}
+
+fn application_destination(job: &Job, issues: &mut Vec<ValidationIssue>) {
+    let destination = job.application_destination.trim();
+    let method = job.application_method.trim().to_ascii_lowercase();
+    if destination.is_empty() {
+        issues.push(issue(
+            job,
+            Severity::Error,
+            "application_destination",
+            "Job has no application destination".to_string(),
+        ));
+        return;
+    }
+
+    let valid = match method.as_str() {
+        "url" => is_url(destination),
+        "email" => is_email(destination),
+        _ => {
+            issues.push(issue(
+                job,
+                Severity::Error,
+                "application_method",
+                format!("Unknown application method {:?}", job.application_method),
+            ));
+            return;
+        }
+    };
+    if !valid {
+        issues.push(issue(
+            job,
+            Severity::Error,
+            "application_destination",
+            format!("{:?} is not a valid {}", destination, method),
+        ));
+    }
+}
+
+/// Postal codes are only checked as Canadian where the job is in Canada, the company's when
+/// none of the locations is elsewhere since it has no country of its own
+fn postal_codes(job: &Job, issues: &mut Vec<ValidationIssue>) {
+    for location in &job.locations {
+        if !is_canadian(location) {
+            continue;
+        }
+        if let Some(message) = check_postal_code(&location.postal_code, &location.province) {
+            issues.push(issue(
+                job,
+                Severity::Warning,
+                "locations.postal_code",
+                message,
+            ));
+        }
+    }
+
+    if !job.locations.iter().all(is_canadian) {
+        return;
+    }
+    if let Some(message) = check_postal_code(&job.company.postal_code, "") {
+        issues.push(issue(
+            job,
+            Severity::Warning,
+            "company.postal_code",
+            message,
+        ));
+    }
+}
+
+/// In a Canadian province, or in a country written as Canada or left empty
+fn is_canadian(location: &Location) -> bool {
+    let country = text::fold(&location.country);
+    location.province.starts_with("CA-") || matches!(country.as_str(), "" | "ca" | "can" | "canada")
+}
+
+fn is_url(value: &str) -> bool {
+    let lower = value.to_ascii_lowercase();
+    let Some(rest) = lower
+        .strip_prefix("https://")
+        .or_else(|| lower.strip_prefix("http://"))
+    else {
+        return false;
+    };
+    let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = host.rsplit_once('@').map_or(host, |(_, host)| host);
+    let host = host.split(':').next().unwrap_or_default();
+
+    !value.contains(char::is_whitespace) && is_domain(host)
+}
+
+fn is_email(value: &str) -> bool {
+    let value = value.strip_prefix("mailto:").unwrap_or(value);
+    match value.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !value.contains(char::is_whitespace)
+                && !domain.contains('@')
+                && is_domain(domain)
+        }
+        None => false,
+    }
+}
+
+fn is_domain(host: &str) -> bool {
+    let labels: Vec<&str> = host.split('.').collect();
+    labels.len() > 1
+        && labels.iter().all(|label| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_alphanumeric() || c == '-')
+        })
+}
+
+/// First letter of the postal codes of each province
+const POSTAL_PREFIXES: &[(&str, &str)] = &[
+    ("CA-NL", "A"),
+    ("CA-NS", "B"),
+    ("CA-PE", "C"),
+    ("CA-NB", "E"),
+    ("CA-QC", "GHJ"),
+    ("CA-ON", "KLMNP"),
+    ("CA-MB", "R"),
+    ("CA-SK", "S"),
+    ("CA-AB", "T"),
+    ("CA-BC", "V"),
+    ("CA-NT", "X"),
+    ("CA-NU", "X"),
+    ("CA-YT", "Y"),
+];
+
+/// Problem with a Canadian postal code (`H2X 1Y4`), if any
+/// `province` is an ISO code, the first letter must belong to it when known
+fn check_postal_code(postal_code: &str, province: &str) -> Option<String> {
+    let compact: Vec<char> = postal_code
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    if compact.is_empty() {
+        return None;
+    }
+
+    // Letters D, F, I, O, Q and U are never used, W and Z never come first
+    let letter = |c: &char| c.is_ascii_uppercase() && !"DFIOQU".contains(*c);
+    let shape = compact.len() == 6
+        && compact.iter().enumerate().all(|(i, c)| {
+            if i % 2 == 0 {
+                letter(c)
+            } else {
+                c.is_ascii_digit()
+            }
+        })
+        && !"WZ".contains(compact[0]);
+    if !shape {
+        return Some(format!("{:?} is not a Canadian postal code", postal_code));
+    }
+
+    match POSTAL_PREFIXES.iter().find(|(code, _)| *code == province) {
+        Some((code, prefixes)) if !prefixes.contains(compact[0]) => {
+            Some(format!("Postal code {:?} is not in {}", postal_code, code))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::common::Company;
+
+    #[test]
+    fn urls_need_a_web_scheme_and_a_domain() {
+        assert!(is_url("https://jobs.hotel.ca/apply?id=1#form"));
+        assert!(is_url("http://recruiter@jobs.hotel.ca:8080/apply"));
+        assert!(is_url("HTTPS://Jobs.Hotel.CA"));
+
+        assert!(!is_url("jobs.hotel.ca/apply"));
+        assert!(!is_url("ftp://jobs.hotel.ca"));
+        assert!(!is_url("https://localhost/apply"));
+        assert!(!is_url("https://jobs.hotel.ca/apply now"));
+        assert!(!is_url("https://-jobs.hotel.ca"));
+    }
+
+    #[test]
+    fn emails_need_a_local_part_and_a_domain() {
+        assert!(is_email("jobs@hotel.ca"));
+        assert!(is_email("mailto:jobs@hotel.ca"));
+
+        assert!(!is_email("jobs.hotel.ca"));
+        assert!(!is_email("@hotel.ca"));
+        assert!(!is_email("jobs@hotel"));
+        assert!(!is_email("jobs@desk@hotel.ca"));
+        assert!(!is_email("mailto: jobs@hotel.ca"));
+        assert!(!is_email("jobs @hotel.ca"));
+    }
+
+    #[test]
+    fn postal_codes_must_belong_to_their_province() {
+        assert_eq!(check_postal_code("H2X 1Y4", "CA-QC"), None);
+        assert_eq!(check_postal_code("h2x-1y4", "CA-QC"), None);
+        assert_eq!(check_postal_code("M5V 2T6", ""), None);
+        assert_eq!(check_postal_code("", "CA-QC"), None);
+
+        assert_eq!(
+            check_postal_code("M5V 2T6", "CA-QC"),
+            Some("Postal code \"M5V 2T6\" is not in CA-QC".to_string())
+        );
+        assert!(check_postal_code("H2X 1Y4", "CA-ON").is_some());
+        assert!(check_postal_code("90210", "CA-QC").is_some());
+        assert!(check_postal_code("Z2X 1Y4", "").is_some());
+    }
+
+    fn job(country: &str, company_postal_code: &str) -> Job {
+        Job {
+            id: "1".to_string(),
+            locations: vec![Location {
+                city: "Plattsburgh".to_string(),
+                postal_code: "12901".to_string(),
+                country: country.to_string(),
+                ..Default::default()
+            }],
+            company: Company {
+                postal_code: company_postal_code.to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn postal_issues(job: &Job) -> Vec<&'static str> {
+        let mut issues = Vec::new();
+        postal_codes(job, &mut issues);
+        issues.iter().map(|issue| issue.field).collect()
+    }
+
+    #[test]
+    fn postal_codes_outside_canada_are_not_checked() {
+        assert!(postal_issues(&job("USA", "12901")).is_empty());
+        assert_eq!(
+            postal_issues(&job("Canada", "12901")),
+            ["locations.postal_code", "company.postal_code"]
+        );
+    }
+}
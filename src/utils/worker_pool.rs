@@ -0,0 +1,136 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use tokio::sync::Semaphore;
+
+pub enum PoolError {
+    /// Every worker is busy and the queue is full
+    Saturated,
+    /// The job panicked or was cancelled
+    Failed(String),
+}
+
+/// Runs CPU-bound work (XML parsing and validation) on Tokio's blocking threads so it never
+/// stalls the async workers, with at most `workers` jobs running and `queue_limit` waiting
+pub struct WorkerPool {
+    permits: Arc<Semaphore>,
+    queue_limit: usize,
+    waiting: AtomicUsize,
+}
+
+impl WorkerPool {
+    pub fn new(workers: usize, queue_limit: usize) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(workers)),
+            queue_limit,
+            waiting: AtomicUsize::new(0),
+        }
+    }
+
+    pub async fn run<F, T>(&self, job: F) -> Result<T, PoolError>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let permit = match self.permits.clone().try_acquire_owned() {
+            Ok(permit) => permit,
+            Err(_) => {
+                // Released on drop, also when the caller stops waiting (client disconnected)
+                let slot = QueueSlot::take(&self.waiting);
+                if slot.position >= self.queue_limit {
+                    return Err(PoolError::Saturated);
+                }
+                self.permits
+                    .clone()
+                    .acquire_owned()
+                    .await
+                    .map_err(|e| PoolError::Failed(e.to_string()))?
+            }
+        };
+
+        // Keep the caller's span (and its request id) on logs emitted by the job
+        let span = tracing::Span::current();
+        tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            let _span = span.enter();
+            job()
+        })
+        .await
+        .map_err(|e| PoolError::Failed(e.to_string()))
+    }
+}
+
+/// A place in the queue, counted in `waiting` until dropped
+struct QueueSlot<'a> {
+    waiting: &'a AtomicUsize,
+    /// Requests that were already waiting
+    position: usize,
+}
+
+impl<'a> QueueSlot<'a> {
+    fn take(waiting: &'a AtomicUsize) -> Self {
+        let position = waiting.fetch_add(1, Ordering::SeqCst);
+        Self { waiting, position }
+    }
+}
+
+impl Drop for QueueSlot<'_> {
+    fn drop(&mut self) {
+        self.waiting.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc, time::Duration};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn cancelled_waiter_frees_its_queue_slot() {
+        let pool = Arc::new(WorkerPool::new(1, 1));
+        let (release, blocked) = mpsc::channel::<()>();
+        let running = tokio::spawn({
+            let pool = pool.clone();
+            async move { pool.run(move || blocked.recv().ok()).await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // Queued behind the running job, then dropped while it waits
+        let waiter = tokio::spawn({
+            let pool = pool.clone();
+            async move { pool.run(|| ()).await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        waiter.abort();
+        let _ = waiter.await;
+        assert_eq!(pool.waiting.load(Ordering::SeqCst), 0);
+
+        let queued = tokio::spawn({
+            let pool = pool.clone();
+            async move { pool.run(|| 42).await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        release.send(()).unwrap();
+        assert!(running.await.unwrap().is_ok());
+        assert!(matches!(queued.await.unwrap(), Ok(42)));
+    }
+
+    #[tokio::test]
+    async fn full_queue_is_saturated() {
+        let pool = Arc::new(WorkerPool::new(1, 0));
+        let (release, blocked) = mpsc::channel::<()>();
+        let running = tokio::spawn({
+            let pool = pool.clone();
+            async move { pool.run(move || blocked.recv().ok()).await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert!(matches!(pool.run(|| ()).await, Err(PoolError::Saturated)));
+        assert_eq!(pool.waiting.load(Ordering::SeqCst), 0);
+        release.send(()).unwrap();
+        assert!(running.await.unwrap().is_ok());
+    }
+}